@@ -0,0 +1,283 @@
+use std::{
+    env, error, fs, io,
+    path::{Path, PathBuf},
+    process,
+    time::Instant,
+};
+
+use code_2015::{
+    check::InputProblem,
+    profile::{self, Bytes, CountingAllocator},
+    report::{self, DayReport, PartReport, Visual},
+};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[path = "../day1.rs"]
+#[allow(dead_code)]
+mod day1;
+#[path = "../day10.rs"]
+#[allow(dead_code)]
+mod day10;
+#[path = "../day11.rs"]
+#[allow(dead_code)]
+mod day11;
+#[path = "../day12.rs"]
+#[allow(dead_code)]
+mod day12;
+#[path = "../day13.rs"]
+#[allow(dead_code)]
+mod day13;
+#[path = "../day14.rs"]
+#[allow(dead_code)]
+mod day14;
+#[path = "../day15.rs"]
+#[allow(dead_code)]
+mod day15;
+#[path = "../day2.rs"]
+#[allow(dead_code)]
+mod day2;
+#[path = "../day3.rs"]
+#[allow(dead_code)]
+mod day3;
+#[path = "../day4.rs"]
+#[allow(dead_code)]
+mod day4;
+#[path = "../day5.rs"]
+#[allow(dead_code)]
+mod day5;
+#[path = "../day6.rs"]
+#[allow(dead_code)]
+mod day6;
+#[path = "../day7.rs"]
+#[allow(dead_code)]
+mod day7;
+#[path = "../day8.rs"]
+#[allow(dead_code)]
+mod day8;
+#[path = "../day9.rs"]
+#[allow(dead_code)]
+mod day9;
+
+mod tui;
+
+type Part = fn(&str) -> Result<String, Box<dyn error::Error>>;
+type Visualize = fn(&str) -> Result<Vec<Visual>, Box<dyn error::Error>>;
+
+struct Day {
+    day: u32,
+    check: fn(&str) -> Vec<InputProblem>,
+    parts: [Part; 2],
+    visualize: Option<Visualize>,
+}
+
+const DAYS: [Day; 15] = [
+    Day {
+        day: 1,
+        check: day1::check_input,
+        parts: [day1::part_one, day1::part_two],
+        visualize: None,
+    },
+    Day {
+        day: 2,
+        check: day2::check_input,
+        parts: [day2::part_one, day2::part_two],
+        visualize: None,
+    },
+    Day {
+        day: 3,
+        check: day3::check_input,
+        parts: [day3::part_one, day3::part_two],
+        visualize: Some(day3::visualize),
+    },
+    Day {
+        day: 4,
+        check: day4::check_input,
+        parts: [day4::part_one, day4::part_two],
+        visualize: None,
+    },
+    Day {
+        day: 5,
+        check: day5::check_input,
+        parts: [day5::part_one, day5::part_two],
+        visualize: None,
+    },
+    Day {
+        day: 6,
+        check: day6::check_input,
+        parts: [day6::part_one, day6::part_two],
+        visualize: Some(day6::visualize),
+    },
+    Day {
+        day: 7,
+        check: day7::check_input,
+        parts: [day7::part_one, day7::part_two],
+        visualize: None,
+    },
+    Day {
+        day: 8,
+        check: day8::check_input,
+        parts: [day8::part_one, day8::part_two],
+        visualize: None,
+    },
+    Day {
+        day: 9,
+        check: day9::check_input,
+        parts: [day9::part_one, day9::part_two],
+        visualize: Some(day9::visualize),
+    },
+    Day {
+        day: 10,
+        check: day10::check_input,
+        parts: [day10::part_one, day10::part_two],
+        visualize: None,
+    },
+    Day {
+        day: 11,
+        check: day11::check_input,
+        parts: [day11::part_one, day11::part_two],
+        visualize: None,
+    },
+    Day {
+        day: 12,
+        check: day12::check_input,
+        parts: [day12::part_one, day12::part_two],
+        visualize: None,
+    },
+    Day {
+        day: 13,
+        check: day13::check_input,
+        parts: [day13::part_one, day13::part_two],
+        visualize: Some(day13::visualize),
+    },
+    Day {
+        day: 14,
+        check: day14::check_input,
+        parts: [day14::part_one, day14::part_two],
+        visualize: Some(day14::visualize),
+    },
+    Day {
+        day: 15,
+        check: day15::check_input,
+        parts: [day15::part_one, day15::part_two],
+        visualize: None,
+    },
+];
+
+fn main() -> io::Result<()> {
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    match args.first().map(|a| a.as_str()) {
+        Some("check") if args.len() == 3 => {
+            let day = find_day(&args[1]);
+            let input = fs::read_to_string(&args[2])?;
+            let problems = (day.check)(input.as_str());
+            for p in problems.iter() {
+                println!("{}: {}", args[2], p);
+            }
+            if !problems.is_empty() {
+                println!("day{}: found {} problem(s)", day.day, problems.len());
+                process::exit(1);
+            }
+            println!("day{}: no problem found", day.day);
+        }
+        Some("report") if args.len() == 3 => {
+            let mut reports = vec![];
+            for day in DAYS.iter() {
+                let path = Path::new(&args[1]).join(format!("day{}.txt", day.day));
+                match fs::read_to_string(&path) {
+                    Ok(input) => reports.push(run_day(day, input.as_str())),
+                    Err(e) => {
+                        eprintln!("skip day{}, read {} failed. {}", day.day, path.display(), e)
+                    }
+                }
+            }
+            fs::write(&args[2], report::render(&reports))?;
+            println!("report for {} day(s) written to {}", reports.len(), args[2]);
+        }
+        Some("profile") if args.len() == 2 || args.len() == 3 => {
+            let days = match args.get(2) {
+                Some(d) => vec![find_day(d)],
+                None => DAYS.iter().collect(),
+            };
+            println!(
+                "{:>4} {:>5} {:>12} {:>12} {:>12} {:>12}",
+                "day", "part", "time", "peak heap", "allocations", "allocated"
+            );
+            for day in days {
+                let path = Path::new(&args[1]).join(format!("day{}.txt", day.day));
+                let input = match fs::read_to_string(&path) {
+                    Ok(input) => input,
+                    Err(e) => {
+                        eprintln!("skip day{}, read {} failed. {}", day.day, path.display(), e);
+                        continue;
+                    }
+                };
+                for p in run_parts(day, input.as_str()) {
+                    if let Err(e) = p.answer {
+                        eprintln!("day{} part {} failed. {}", day.day, p.part, e);
+                    }
+                    println!(
+                        "{:>4} {:>5} {:>12} {:>12} {:>12} {:>12}",
+                        day.day,
+                        p.part,
+                        format!("{:.3?}", p.elapsed),
+                        Bytes(p.memory.peak_bytes).to_string(),
+                        p.memory.allocations,
+                        Bytes(p.memory.allocated_bytes).to_string()
+                    );
+                }
+            }
+        }
+        Some("tui") if args.len() == 2 => tui::run(PathBuf::from(&args[1]))?,
+        _ => {
+            eprintln!("usage: aoc check <day> <file>");
+            eprintln!("       aoc report <input-dir> <output.html>");
+            eprintln!("       aoc profile <input-dir> [day]");
+            eprintln!("       aoc tui <input-dir>");
+            process::exit(2);
+        }
+    }
+    Ok(())
+}
+
+fn run_parts(day: &Day, input: &str) -> Vec<PartReport> {
+    day.parts
+        .iter()
+        .enumerate()
+        .map(|(i, part)| {
+            let start = Instant::now();
+            let (answer, memory) = profile::measure(|| part(input).map_err(|e| e.to_string()));
+            PartReport {
+                part: i as u32 + 1,
+                answer,
+                elapsed: start.elapsed(),
+                memory,
+            }
+        })
+        .collect()
+}
+
+fn run_day(day: &Day, input: &str) -> DayReport {
+    let parts = run_parts(day, input);
+    let visuals = day
+        .visualize
+        .map_or(Ok(vec![]), |visualize| visualize(input))
+        .map_err(|e| e.to_string());
+    DayReport {
+        day: day.day,
+        parts,
+        visuals,
+    }
+}
+
+fn find_day(s: &str) -> &'static Day {
+    s.trim_start_matches("day")
+        .parse::<u32>()
+        .ok()
+        .and_then(|d| DAYS.iter().find(|day| day.day == d))
+        .unwrap_or_else(|| {
+            eprintln!("unknown day: \"{}\"", s);
+            process::exit(2);
+        })
+}
@@ -0,0 +1,333 @@
+use std::{
+    fmt, fs, io,
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    sync::{mpsc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use code_2015::trace;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, List, ListItem, ListState, Paragraph, Row, Table},
+    DefaultTerminal, Frame,
+};
+use tracing::{
+    field::{Field, Visit},
+    Subscriber,
+};
+use tracing_subscriber::{filter::LevelFilter, layer::Context, prelude::*, Layer};
+
+use crate::DAYS;
+
+enum Message {
+    Progress(String),
+    PartStarted(u32),
+    PartDone(RunRecord),
+    Finished,
+}
+
+struct RunRecord {
+    run: usize,
+    part: u32,
+    answer: Result<String, String>,
+    elapsed: Duration,
+}
+
+struct Running {
+    day: usize,
+    part: u32,
+    started: Instant,
+    progress: String,
+}
+
+struct App {
+    input_dir: PathBuf,
+    days: ListState,
+    has_input: Vec<bool>,
+    history: Vec<Vec<RunRecord>>,
+    runs: usize,
+    running: Option<Running>,
+    status: String,
+    sender: mpsc::Sender<Message>,
+    redraw: bool,
+}
+
+/// Forwards solver tracing events to the dashboard as progress lines, so the
+/// dashboard reuses the progress events the solvers already emit.
+struct ProgressLayer {
+    sender: Mutex<mpsc::Sender<Message>>,
+}
+
+impl<S: Subscriber> Layer<S> for ProgressLayer {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = EventFields::default();
+        event.record(&mut fields);
+        let line = match fields.message {
+            Some(m) => format!("{}: {}", m, fields.values.join(" ")),
+            None => fields.values.join(" "),
+        };
+        let _ = self.sender.lock().unwrap().send(Message::Progress(line));
+    }
+}
+
+#[derive(Default)]
+struct EventFields {
+    message: Option<String>,
+    values: Vec<String>,
+}
+
+impl Visit for EventFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.values.push(format!("{}={}", field.name(), value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = Some(format!("{:?}", value));
+        } else {
+            self.values.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+}
+
+/// Runs the dashboard until the user quits, reading `dayN.txt` inputs from
+/// `input_dir`.
+pub fn run(input_dir: PathBuf) -> io::Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    let layer = ProgressLayer {
+        sender: Mutex::new(sender.clone()),
+    };
+    tracing::subscriber::set_global_default(
+        tracing_subscriber::registry().with(layer.with_filter(LevelFilter::DEBUG)),
+    )
+    .map_err(io::Error::other)?;
+
+    let mut app = App::new(input_dir, sender);
+    let mut terminal = ratatui::init();
+    let ret = app.run(&mut terminal, receiver);
+    ratatui::restore();
+    ret
+}
+
+impl App {
+    fn new(input_dir: PathBuf, sender: mpsc::Sender<Message>) -> App {
+        let has_input = DAYS
+            .iter()
+            .map(|d| input_dir.join(format!("day{}.txt", d.day)).is_file())
+            .collect();
+        App {
+            input_dir,
+            days: ListState::default().with_selected(Some(0)),
+            has_input,
+            history: DAYS.iter().map(|_| vec![]).collect(),
+            runs: 0,
+            running: None,
+            status: "press enter to run the selected day".into(),
+            sender,
+            redraw: false,
+        }
+    }
+
+    fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        receiver: mpsc::Receiver<Message>,
+    ) -> io::Result<()> {
+        loop {
+            if self.redraw {
+                terminal.clear()?;
+                self.redraw = false;
+            }
+            terminal.draw(|f| self.draw(f))?;
+            if event::poll(Duration::from_millis(100))? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press {
+                        match key.code {
+                            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                            KeyCode::Up | KeyCode::Char('k') => self.days.select_previous(),
+                            KeyCode::Down | KeyCode::Char('j') => self.days.select_next(),
+                            KeyCode::Enter => self.start(),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            while let Ok(m) = receiver.try_recv() {
+                self.handle(m);
+            }
+        }
+    }
+
+    fn selected(&self) -> usize {
+        self.days.selected().unwrap_or(0).min(DAYS.len() - 1)
+    }
+
+    fn start(&mut self) {
+        if self.running.is_some() {
+            return;
+        }
+        let index = self.selected();
+        let day = &DAYS[index];
+        let path = self.input_dir.join(format!("day{}.txt", day.day));
+        let input = match fs::read_to_string(&path) {
+            Ok(input) => input,
+            Err(e) => {
+                self.status = format!("read {} failed. {}", path.display(), e);
+                return;
+            }
+        };
+
+        self.runs += 1;
+        let run = self.runs;
+        let (number, parts) = (day.day, day.parts);
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            for (i, part) in parts.iter().enumerate() {
+                let part_number = i as u32 + 1;
+                let _ = sender.send(Message::PartStarted(part_number));
+                let started = Instant::now();
+                let answer = panic::catch_unwind(AssertUnwindSafe(|| {
+                    trace::solve(number, part_number, || part(input.as_str()))
+                        .map_err(|e| e.to_string())
+                }))
+                .unwrap_or_else(|_| Err("solver panicked".into()));
+                let _ = sender.send(Message::PartDone(RunRecord {
+                    run,
+                    part: part_number,
+                    answer,
+                    elapsed: started.elapsed(),
+                }));
+            }
+            let _ = sender.send(Message::Finished);
+        });
+        self.running = Some(Running {
+            day: index,
+            part: 1,
+            started: Instant::now(),
+            progress: String::new(),
+        });
+        self.status = format!("running day {}", number);
+    }
+
+    fn handle(&mut self, message: Message) {
+        let running = match self.running.as_mut() {
+            Some(r) => r,
+            None => return,
+        };
+        match message {
+            Message::Progress(line) => running.progress = line,
+            Message::PartStarted(part) => {
+                running.part = part;
+                running.progress.clear();
+            }
+            Message::PartDone(record) => {
+                if record.answer.is_err() {
+                    // A panicking solver writes to the terminal behind our back.
+                    self.redraw = true;
+                }
+                self.history[running.day].push(record);
+            }
+            Message::Finished => {
+                self.status = format!(
+                    "day {} finished in {:.3?}",
+                    DAYS[running.day].day,
+                    running.started.elapsed()
+                );
+                self.running = None;
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, footer] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [list_area, detail_area] =
+            Layout::horizontal([Constraint::Length(24), Constraint::Min(0)]).areas(main);
+        let [status_area, history_area] =
+            Layout::vertical([Constraint::Length(6), Constraint::Min(0)]).areas(detail_area);
+
+        let items = DAYS
+            .iter()
+            .enumerate()
+            .map(|(i, d)| {
+                let mark = match (&self.running, self.history[i].last()) {
+                    (Some(r), _) if r.day == i => "running",
+                    (_, Some(last)) if last.answer.is_err() => "failed",
+                    (_, Some(_)) => "done",
+                    _ if !self.has_input[i] => "no input",
+                    _ => "",
+                };
+                ListItem::new(format!("Day {:<3} {}", d.day, mark))
+            })
+            .collect::<Vec<ListItem>>();
+        let list = List::new(items)
+            .block(Block::bordered().title("Days"))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(list, list_area, &mut self.days);
+
+        let selected = self.selected();
+        let day = &DAYS[selected];
+        let mut lines = vec![Line::from(format!(
+            "input: {}",
+            self.input_dir.join(format!("day{}.txt", day.day)).display()
+        ))];
+        match &self.running {
+            Some(r) if r.day == selected => {
+                lines.push(Line::from(format!(
+                    "running part {} for {:.1?}",
+                    r.part,
+                    r.started.elapsed()
+                )));
+                lines.push(Line::from(format!("progress: {}", r.progress)));
+            }
+            _ => lines.push(Line::from("idle")),
+        }
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(format!("Day {}", day.day))),
+            status_area,
+        );
+
+        let rows = self.history[selected].iter().rev().map(|r| {
+            let (answer, style) = match &r.answer {
+                Ok(a) => (a.clone(), Style::default()),
+                Err(e) => (e.clone(), Style::default().fg(Color::Red)),
+            };
+            Row::new(vec![
+                r.run.to_string(),
+                r.part.to_string(),
+                answer,
+                format!("{:.3?}", r.elapsed),
+            ])
+            .style(style)
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(5),
+                Constraint::Length(5),
+                Constraint::Min(20),
+                Constraint::Length(14),
+            ],
+        )
+        .header(
+            Row::new(vec!["Run", "Part", "Answer", "Time"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::bordered().title("History"));
+        frame.render_widget(table, history_area);
+
+        frame.render_widget(
+            Paragraph::new(format!(
+                "up/down select, enter run, q quit | {}",
+                self.status
+            )),
+            footer,
+        );
+    }
+}
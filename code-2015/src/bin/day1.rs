@@ -1,15 +1,84 @@
-use std::io;
+use std::{
+    collections::BTreeMap,
+    env, error, fmt,
+    io::{self, Read},
+    iter::Enumerate,
+    str::Chars,
+};
+
+use code_2015::{
+    check::{check_single_line, InputProblem},
+    trace,
+};
 
 fn main() -> io::Result<()> {
+    trace::init();
+    let options = match Options::from_args(env::args().skip(1)) {
+        Ok(o) => o,
+        Err(e) => {
+            println!("{}", e);
+            println!("usage: day1 [--strict | --lenient] [--stats] [--target <floor>]...");
+            println!("       day1 --fast");
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, e));
+        }
+    };
+    if options.fast {
+        let ret = trace::solve_all(1, || check_result_from_reader(io::stdin().lock()))?;
+        println!(
+            "Santa is at floor: {} and his first time to basement is at position: {}",
+            ret.floor, ret.position
+        );
+        return Ok(());
+    }
     let mut buffer = String::new();
-    let read_ret = io::stdin().read_line(&mut buffer);
+    let read_ret = trace::parse(1, || match options.mode {
+        Mode::Permissive => io::stdin().read_line(&mut buffer),
+        Mode::Lenient | Mode::Strict => io::stdin().read_to_string(&mut buffer),
+    });
     match read_ret {
         Ok(_) => {
-            let ret = check_result(buffer.as_str());
+            let ret =
+                match trace::solve_all(1, || check_result_with_mode(buffer.as_str(), options.mode))
+                {
+                    Ok(r) => r,
+                    Err(invalid) => {
+                        for c in invalid.iter() {
+                            println!("{}", c);
+                        }
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("found {} invalid character(s)", invalid.len()),
+                        ));
+                    }
+                };
             println!(
                 "Santa is at floor: {} and his first time to basement is at position: {}",
                 ret.floor, ret.position
             );
+
+            let instructions = instructions(buffer.as_str(), options.mode);
+            let instructions = instructions.as_str();
+            if options.stats {
+                let stats = trajectory_stats(instructions);
+                println!(
+                    "highest floor: {} first reached at position: {}",
+                    stats.highest.0, stats.highest.1
+                );
+                println!(
+                    "lowest floor: {} first reached at position: {}",
+                    stats.lowest.0, stats.lowest.1
+                );
+                println!("entered the basement {} times", stats.basement_entries);
+                for (floor, steps) in stats.steps_per_floor {
+                    println!("spent {} steps on floor: {}", steps, floor);
+                }
+            }
+            for target in options.targets {
+                match first_position_at(instructions, target) {
+                    Some(p) => println!("Santa first reaches floor: {} at position: {}", target, p),
+                    None => println!("Santa never reaches floor: {}", target),
+                }
+            }
             return Ok(());
         }
         Err(err) => {
@@ -19,6 +88,55 @@ fn main() -> io::Result<()> {
     }
 }
 
+struct Options {
+    fast: bool,
+    mode: Mode,
+    stats: bool,
+    targets: Vec<i32>,
+}
+
+impl Options {
+    fn from_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options {
+            fast: false,
+            mode: Mode::Permissive,
+            stats: false,
+            targets: vec![],
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--fast" => options.fast = true,
+                "--strict" => options.mode = Mode::Strict,
+                "--lenient" => options.mode = Mode::Lenient,
+                "--stats" => options.stats = true,
+                "--target" => {
+                    let floor = args.next().ok_or("missing floor after --target")?;
+                    options.targets.push(
+                        floor
+                            .parse::<i32>()
+                            .map_err(|_| format!("invalid target floor: \"{}\"", floor))?,
+                    );
+                }
+                _ => return Err(format!("unknown option: \"{}\"", arg)),
+            }
+        }
+        if options.fast
+            && (options.mode != Mode::Permissive || options.stats || !options.targets.is_empty())
+        {
+            return Err("--fast can't be combined with other options".into());
+        }
+        Ok(options)
+    }
+}
+
+pub fn part_one(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(check_result(input).floor.to_string())
+}
+
+pub fn part_two(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(check_result(input).position.to_string())
+}
+
 #[derive(PartialEq, Debug)]
 struct SantaResult {
     floor: i32,
@@ -47,6 +165,220 @@ fn check_result(input: &str) -> SantaResult {
     return SantaResult { floor, position };
 }
 
+const READ_CHUNK: usize = 1 << 16;
+const BLOCK: usize = 64;
+
+/// Same as `check_result` but reads the instructions as raw bytes from a
+/// stream, so inputs of hundreds of megabytes never need to be in memory.
+///
+/// Blocks are reduced to their floor change with branch free counting that
+/// the compiler vectorizes. A block is only walked byte by byte, as a prefix
+/// sum, while the basement is still unvisited and within its reach.
+fn check_result_from_reader(mut reader: impl Read) -> io::Result<SantaResult> {
+    let mut buffer = vec![0; READ_CHUNK];
+    let mut floor: i64 = 0;
+    let mut chars: usize = 0;
+    let mut position: usize = 0;
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        for block in buffer[..n].chunks(BLOCK) {
+            if position == 0 && floor < block.len() as i64 {
+                for &b in block {
+                    chars += !is_utf8_continuation(b) as usize;
+                    floor += floor_delta(b) as i64;
+                    if floor == -1 && position == 0 {
+                        position = chars;
+                    }
+                }
+            } else {
+                floor += block.iter().map(|&b| floor_delta(b) as i64).sum::<i64>();
+                chars += block.iter().filter(|&&b| !is_utf8_continuation(b)).count();
+            }
+        }
+    }
+    let floor = i32::try_from(floor).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("floor: {} doesn't fit in i32", floor),
+        )
+    })?;
+    Ok(SantaResult { floor, position })
+}
+
+fn floor_delta(b: u8) -> i8 {
+    (b == b'(') as i8 - (b == b')') as i8
+}
+
+/// Positions count chars like `check_result` does, and every byte of a UTF-8
+/// encoded char except the first one is a continuation byte.
+fn is_utf8_continuation(b: u8) -> bool {
+    b & 0b1100_0000 == 0b1000_0000
+}
+
+/// How characters other than `(` and `)` are treated.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Mode {
+    /// Any other character is counted as a position but doesn't move Santa.
+    Permissive,
+    /// Whitespace is skipped and doesn't count as a position, anything else
+    /// is rejected.
+    Lenient,
+    /// Only a single line terminator at the end of the input is allowed.
+    Strict,
+}
+
+#[derive(PartialEq, Debug)]
+struct InvalidCharacter {
+    /// Byte offset in the input.
+    offset: usize,
+    line: usize,
+    column: usize,
+    character: char,
+}
+
+impl fmt::Display for InvalidCharacter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid character {:?} at offset: {} (line: {}, column: {})",
+            self.character, self.offset, self.line, self.column
+        )
+    }
+}
+
+fn check_result_with_mode(input: &str, mode: Mode) -> Result<SantaResult, Vec<InvalidCharacter>> {
+    let invalid = invalid_characters(input, mode);
+    if !invalid.is_empty() {
+        return Err(invalid);
+    }
+    Ok(check_result(instructions(input, mode).as_str()))
+}
+
+fn invalid_characters(input: &str, mode: Mode) -> Vec<InvalidCharacter> {
+    let checked = match mode {
+        Mode::Permissive => return vec![],
+        Mode::Lenient => input,
+        Mode::Strict => strip_line_terminator(input),
+    };
+    let mut invalid = vec![];
+    let mut line = 1;
+    let mut column = 0;
+    for (offset, c) in checked.char_indices() {
+        column += 1;
+        if c != '(' && c != ')' && !(mode == Mode::Lenient && c.is_whitespace()) {
+            invalid.push(InvalidCharacter {
+                offset,
+                line,
+                column,
+                character: c,
+            });
+        }
+        if c == '\n' {
+            line += 1;
+            column = 0;
+        }
+    }
+    invalid
+}
+
+/// The instructions Santa follows from a valid input in the given mode.
+fn instructions(input: &str, mode: Mode) -> String {
+    match mode {
+        Mode::Permissive => input.trim_end().into(),
+        Mode::Lenient => input.chars().filter(|c| !c.is_whitespace()).collect(),
+        Mode::Strict => strip_line_terminator(input).into(),
+    }
+}
+
+fn strip_line_terminator(input: &str) -> &str {
+    input
+        .strip_suffix('\n')
+        .map(|s| s.strip_suffix('\r').unwrap_or(s))
+        .unwrap_or(input)
+}
+
+/// Walks the instructions and yields `(position, floor)` after every
+/// instruction, positions start at 1 like in `SantaResult`.
+struct Trajectory<'a> {
+    instructions: Enumerate<Chars<'a>>,
+    floor: i32,
+}
+
+impl Iterator for Trajectory<'_> {
+    type Item = (usize, i32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (i, c) = self.instructions.next()?;
+        self.floor += match c {
+            '(' => 1,
+            ')' => -1,
+            _ => 0,
+        };
+        Some((i + 1, self.floor))
+    }
+}
+
+fn trajectory(input: &str) -> Trajectory<'_> {
+    Trajectory {
+        instructions: input.chars().enumerate(),
+        floor: 0,
+    }
+}
+
+#[derive(PartialEq, Debug)]
+struct TrajectoryStats {
+    /// Highest floor and the first position reaching it, `(0, 0)` is the start.
+    highest: (i32, usize),
+    lowest: (i32, usize),
+    /// How many times Santa went down from floor 0 into the basement.
+    basement_entries: usize,
+    /// Number of instructions after which Santa stands on each floor.
+    steps_per_floor: BTreeMap<i32, usize>,
+}
+
+fn trajectory_stats(input: &str) -> TrajectoryStats {
+    let mut stats = TrajectoryStats {
+        highest: (0, 0),
+        lowest: (0, 0),
+        basement_entries: 0,
+        steps_per_floor: BTreeMap::new(),
+    };
+    let mut last_floor = 0;
+    for (position, floor) in trajectory(input) {
+        if floor > stats.highest.0 {
+            stats.highest = (floor, position);
+        }
+        if floor < stats.lowest.0 {
+            stats.lowest = (floor, position);
+        }
+        if floor < 0 && last_floor >= 0 {
+            stats.basement_entries += 1;
+        }
+        *stats.steps_per_floor.entry(floor).or_insert(0) += 1;
+        last_floor = floor;
+    }
+    stats
+}
+
+/// First position at which Santa stands on `target`, `Some(0)` for floor 0.
+fn first_position_at(input: &str, target: i32) -> Option<usize> {
+    if target == 0 {
+        return Some(0);
+    }
+    trajectory(input)
+        .find(|(_, floor)| *floor == target)
+        .map(|(position, _)| position)
+}
+
+pub fn check_input(input: &str) -> Vec<InputProblem> {
+    check_single_line(input, |c| c == '(' || c == ')')
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,6 +431,137 @@ mod tests {
         );
     }
     #[test]
+    fn test_trajectory() {
+        assert_eq!(
+            trajectory("(()x)").collect::<Vec<(usize, i32)>>(),
+            vec![(1, 1), (2, 2), (3, 1), (4, 1), (5, 0)]
+        );
+        assert_eq!(trajectory("").next(), None);
+    }
+    #[test]
+    fn test_trajectory_stats() {
+        assert_eq!(
+            trajectory_stats("))((((()))"),
+            TrajectoryStats {
+                highest: (3, 7),
+                lowest: (-2, 2),
+                basement_entries: 1,
+                steps_per_floor: BTreeMap::from([(-2, 1), (-1, 2), (0, 2), (1, 2), (2, 2), (3, 1)]),
+            }
+        );
+        assert_eq!(trajectory_stats("()())()))").basement_entries, 2);
+    }
+    #[test]
+    fn test_first_position_at() {
+        assert_eq!(first_position_at("(()))(", -1), Some(5));
+        assert_eq!(first_position_at("(()))(", 2), Some(2));
+        assert_eq!(first_position_at("(()))(", 3), None);
+        assert_eq!(first_position_at("", 0), Some(0));
+    }
+    #[test]
+    fn test_strict_mode() {
+        assert_eq!(
+            check_result_with_mode("(()))\n", Mode::Strict),
+            Ok(SantaResult {
+                floor: -1,
+                position: 5
+            })
+        );
+        assert_eq!(
+            check_result_with_mode("([)\n)x\n\n", Mode::Strict),
+            Err(vec![
+                InvalidCharacter {
+                    offset: 1,
+                    line: 1,
+                    column: 2,
+                    character: '['
+                },
+                InvalidCharacter {
+                    offset: 3,
+                    line: 1,
+                    column: 4,
+                    character: '\n'
+                },
+                InvalidCharacter {
+                    offset: 5,
+                    line: 2,
+                    column: 2,
+                    character: 'x'
+                },
+                InvalidCharacter {
+                    offset: 6,
+                    line: 2,
+                    column: 3,
+                    character: '\n'
+                },
+            ])
+        );
+    }
+    #[test]
+    fn test_lenient_mode() {
+        assert_eq!(
+            check_result_with_mode(" ( )\n\t))\n", Mode::Lenient),
+            Ok(SantaResult {
+                floor: -2,
+                position: 3
+            })
+        );
+        assert_eq!(
+            check_result_with_mode("(\n<p>", Mode::Lenient)
+                .unwrap_err()
+                .iter()
+                .map(|c| (c.line, c.column, c.character))
+                .collect::<Vec<(usize, usize, char)>>(),
+            vec![(2, 1, '<'), (2, 2, 'p'), (2, 3, '>')]
+        );
+    }
+    #[test]
+    fn test_check_result_from_reader() {
+        let mut long = String::new();
+        let mut seed: u32 = 7;
+        for i in 0..300_000 {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            long.push(match (seed >> 16) % 7 {
+                0..=2 => '(',
+                3..=5 => ')',
+                _ if i % 2 == 0 => 'é',
+                _ => '\n',
+            });
+        }
+        let late_basement = format!("{}{}", "(".repeat(200_000), ")".repeat(200_001));
+        for input in [
+            "",
+            "(",
+            ")",
+            "(()))((",
+            "日本)",
+            "((()))\n",
+            long.as_str(),
+            late_basement.as_str(),
+        ] {
+            assert_eq!(
+                check_result_from_reader(input.as_bytes()).unwrap(),
+                check_result(input)
+            );
+            assert_eq!(
+                check_result_from_reader(SlowReader(input.as_bytes())).unwrap(),
+                check_result(input)
+            );
+        }
+    }
+
+    /// Hands out at most 7 bytes per read to exercise chunk boundaries.
+    struct SlowReader<'a>(&'a [u8]);
+
+    impl Read for SlowReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.0.len()).min(7);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+    #[test]
     fn test_position() {
         assert_eq!(
             check_result("(()))(("),
@@ -1,24 +1,46 @@
-use std::io;
+use std::{error, io};
+
+use code_2015::{
+    check::{check_single_line, InputProblem},
+    trace,
+};
 
 fn main() -> io::Result<()> {
+    trace::init();
     let mut buffer = String::new();
-    io::stdin().read_line(&mut buffer).unwrap();
-    let ret = look_and_say(buffer.trim(), 40);
+    trace::parse(10, || io::stdin().read_line(&mut buffer).unwrap());
+    let ret = trace::solve(10, 1, || look_and_say(buffer.trim(), 40));
     println!("length of the result is: {}", ret.len());
 
-    let ret2 = look_and_say(ret.as_str(), 10);
+    let ret2 = trace::solve(10, 2, || look_and_say(ret.as_str(), 10));
     println!("length of the result is: {}", ret2.len());
     Ok(())
 }
 
+pub fn part_one(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(look_and_say(input.trim(), 40).len().to_string())
+}
+
+pub fn part_two(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(look_and_say(input.trim(), 50).len().to_string())
+}
+
 fn look_and_say(s: &str, repeat_times: u16) -> String {
-    let mut last = s.into();
-    for _ in 0..repeat_times {
+    let mut last: String = s.into();
+    for iteration in 0..repeat_times {
         last = do_look_and_say(last);
+        tracing::debug!(iteration, length = last.len(), "look and say");
     }
     return last;
 }
 
+pub fn check_input(input: &str) -> Vec<InputProblem> {
+    if input.trim().is_empty() {
+        return vec![InputProblem::in_input("sequence is empty")];
+    }
+    check_single_line(input.trim_end(), |c| c.is_ascii_digit())
+}
+
 fn do_look_and_say(s: String) -> String {
     let mut out = String::new();
     let mut last = None;
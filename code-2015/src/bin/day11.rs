@@ -1,26 +1,53 @@
-use std::{char::from_u32_unchecked, io};
+use std::{char::from_u32_unchecked, error, io};
+
+use code_2015::{
+    check::{check_single_line, InputProblem},
+    trace,
+};
 
 fn main() -> io::Result<()> {
+    trace::init();
     let mut buffer = String::new();
-    io::stdin().read_line(&mut buffer).unwrap();
+    trace::parse(11, || io::stdin().read_line(&mut buffer).unwrap());
 
-    let mut pass = generate_next_valid_password(buffer);
+    let mut pass = trace::solve(11, 1, || generate_next_valid_password(buffer));
     println!("next valid password is: {}", pass);
-    pass = generate_next_valid_password(pass);
+    pass = trace::solve(11, 2, || generate_next_valid_password(pass));
     println!("next next valid password is: {}", pass);
     Ok(())
 }
 
+pub fn part_one(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(generate_next_valid_password(input.trim().into()))
+}
+
+pub fn part_two(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(generate_next_valid_password(generate_next_valid_password(
+        input.trim().into(),
+    )))
+}
+
 fn generate_next_valid_password(s: String) -> String {
     let mut pass = s;
+    let mut candidates: u64 = 0;
     loop {
         pass = generate_next_password(&pass);
+        candidates += 1;
+        tracing::trace!(candidate = pass.as_str(), "password candidate");
         if is_valid(&pass) {
+            tracing::debug!(password = pass.as_str(), candidates, "valid password");
             return pass;
         }
     }
 }
 
+pub fn check_input(input: &str) -> Vec<InputProblem> {
+    if input.trim().is_empty() {
+        return vec![InputProblem::in_input("password is empty")];
+    }
+    check_single_line(input.trim_end(), |c| c.is_ascii_lowercase())
+}
+
 fn generate_next_password(s: &String) -> String {
     let mut out = String::new();
     let mut pass = false;
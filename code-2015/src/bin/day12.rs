@@ -1,19 +1,32 @@
 use std::{error, io};
 
+use code_2015::{
+    check::{check_single_line, InputProblem},
+    trace,
+};
 use serde_json::Value;
 
 fn main() -> io::Result<()> {
+    trace::init();
     let mut buffer = String::new();
-    io::stdin().read_line(&mut buffer).unwrap();
+    trace::parse(12, || io::stdin().read_line(&mut buffer).unwrap());
 
-    let ret = sum_all_numbers(buffer.as_str());
+    let ret = trace::solve(12, 1, || sum_all_numbers(buffer.as_str()));
     println!("result is {}", ret);
 
-    let ret2 = sum_without_red(buffer.as_str()).unwrap();
+    let ret2 = trace::solve(12, 2, || sum_without_red(buffer.as_str())).unwrap();
     println!("result is {}", ret2);
     Ok(())
 }
 
+pub fn part_one(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(sum_all_numbers(input.lines().next().unwrap_or("")).to_string())
+}
+
+pub fn part_two(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(sum_without_red(input.lines().next().unwrap_or(""))?.to_string())
+}
+
 fn sum_all_numbers(s: &str) -> i32 {
     SearchNumberJsonDocument { input: s.into() }
         .map(|s| s.parse::<i32>().unwrap_or(0))
@@ -25,6 +38,15 @@ fn sum_without_red(s: &str) -> Result<i64, Box<dyn error::Error>> {
     return Ok(sum_number(&v));
 }
 
+pub fn check_input(input: &str) -> Vec<InputProblem> {
+    let mut problems = check_single_line(input, |_| true);
+    let first_line = input.lines().next().unwrap_or("");
+    if let Err(e) = serde_json::from_str::<Value>(first_line) {
+        problems.insert(0, InputProblem::at(1, e.column(), e.to_string()));
+    }
+    problems
+}
+
 struct SearchNumberJsonDocument {
     input: String,
 }
@@ -5,19 +5,30 @@ use std::{
     vec,
 };
 
+use code_2015::{
+    check::{check_lines, sort_by_line, InputProblem},
+    report::Visual,
+    trace,
+};
+
 fn main() -> io::Result<()> {
-    match io::stdin()
-        .lines()
-        .collect::<Result<Vec<String>, _>>()?
-        .iter()
-        .map(|l| l.parse::<Survey>())
-        .collect::<Result<Vec<Survey>, Box<dyn error::Error>>>()
-    {
+    trace::init();
+    match trace::parse(13, || {
+        io::stdin()
+            .lines()
+            .collect::<Result<Vec<String>, _>>()
+            .map(|lines| {
+                lines
+                    .iter()
+                    .map(|l| l.parse::<Survey>())
+                    .collect::<Result<Vec<Survey>, Box<dyn error::Error>>>()
+            })
+    })? {
         Ok(surveys) => {
             let p = Puzzle::new(surveys);
-            let (plan, happiness) = p.optimal_plan();
+            let (plan, happiness) = trace::solve(13, 1, || p.optimal_plan());
             println!("optimal happiness is {}, plan is: {:?}", happiness, plan);
-            let (plan, happiness) = p.optimal_plan_with_me();
+            let (plan, happiness) = trace::solve(13, 2, || p.optimal_plan_with_me());
             println!(
                 "optimal happiness with me is {}, plan is: {:?}",
                 happiness, plan
@@ -28,6 +39,45 @@ fn main() -> io::Result<()> {
     Ok(())
 }
 
+pub fn part_one(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(parse_puzzle(input)?.optimal_plan().1.to_string())
+}
+
+pub fn part_two(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(parse_puzzle(input)?.optimal_plan_with_me().1.to_string())
+}
+
+pub fn visualize(input: &str) -> Result<Vec<Visual>, Box<dyn error::Error>> {
+    let p = parse_puzzle(input)?;
+    let seating = |(plan, happiness): (Vec<&String>, i32), title: &str| {
+        let links = (0..plan.len())
+            .map(|i| {
+                let a = plan[i];
+                let b = plan[(i + 1) % plan.len()];
+                (p.survey_map[a][b] + p.survey_map[b][a]).to_string()
+            })
+            .collect();
+        Visual::Ring {
+            title: format!("{}, happiness {}", title, happiness),
+            nodes: plan.into_iter().cloned().collect(),
+            links,
+            closed: true,
+        }
+    };
+    Ok(vec![
+        seating(p.optimal_plan(), "Optimal seating"),
+        seating(p.optimal_plan_with_me(), "Optimal seating with me"),
+    ])
+}
+
+fn parse_puzzle(input: &str) -> Result<Puzzle, Box<dyn error::Error>> {
+    let surveys = input
+        .lines()
+        .map(|l| l.parse::<Survey>())
+        .collect::<Result<Vec<Survey>, _>>()?;
+    Ok(Puzzle::new(surveys))
+}
+
 struct Puzzle {
     survey_map: HashMap<String, HashMap<String, i32>>,
     guests: HashSet<String>,
@@ -53,18 +103,26 @@ impl Puzzle {
 
     fn optimal_plan(&self) -> (Vec<&String>, i32) {
         let plans = permutations(self.guests.iter().collect::<Vec<&String>>());
-
-        let max = plans.iter().map(|p| self.get_happiness(p)).max().unwrap();
-        let plan = plans.iter().find(|p| self.get_happiness(p) == max).unwrap();
-        return (plan.clone(), max);
+        self.best_plan(plans)
     }
 
     fn optimal_plan_with_me(&self) -> (Vec<&String>, i32) {
         let plans = permutations(self.guests_and_me.iter().collect::<Vec<&String>>());
+        self.best_plan(plans)
+    }
 
-        let max = plans.iter().map(|p| self.get_happiness(p)).max().unwrap();
+    fn best_plan<'a>(&self, plans: Vec<Vec<&'a String>>) -> (Vec<&'a String>, i32) {
+        let max = plans
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                trace::permutations_evaluated(i + 1, plans.len());
+                self.get_happiness(p)
+            })
+            .max()
+            .unwrap();
         let plan = plans.iter().find(|p| self.get_happiness(p) == max).unwrap();
-        return (plan.clone(), max);
+        (plan.clone(), max)
     }
 
     fn get_happiness(&self, plan: &Vec<&String>) -> i32 {
@@ -144,6 +202,48 @@ fn add_me_to_survey_map(
     survey_map
 }
 
+pub fn check_input(input: &str) -> Vec<InputProblem> {
+    let (surveys, mut problems) = check_lines::<Survey>(input);
+    let mut surveyed: HashMap<(&String, &String), usize> = HashMap::new();
+    let mut guests: Vec<&String> = vec![];
+    for (line, s) in surveys.iter() {
+        for g in [&s.from, &s.to] {
+            if !guests.contains(&g) {
+                guests.push(g);
+            }
+        }
+        if s.from == s.to {
+            problems.push(InputProblem::at_line(
+                *line,
+                format!("{} is surveyed about sitting next to themself", s.from),
+            ));
+        } else if let Some(first) = surveyed.get(&(&s.from, &s.to)) {
+            problems.push(InputProblem::at_line(
+                *line,
+                format!(
+                    "{} next to {} is already surveyed on line {}",
+                    s.from, s.to, first
+                ),
+            ));
+        } else {
+            surveyed.insert((&s.from, &s.to), *line);
+        }
+    }
+
+    for from in guests.iter() {
+        for to in guests.iter() {
+            if from != to && !surveyed.contains_key(&(from, to)) {
+                problems.push(InputProblem::in_input(format!(
+                    "happiness of {} sitting next to {} is not surveyed",
+                    from, to
+                )));
+            }
+        }
+    }
+    sort_by_line(&mut problems);
+    problems
+}
+
 #[derive(Debug)]
 struct Survey {
     from: String,
@@ -206,8 +306,28 @@ mod tests {
         .map(|s| s.parse::<Survey>().unwrap())
         .collect();
         let p = Puzzle::new(surveys);
-        let (plan, happiness) = p.optimal_plan();
-        println!("plan {:?} {}", plan, happiness);
+        let (_, happiness) = p.optimal_plan();
         assert_eq!(happiness, 330);
     }
+    #[test]
+    fn test_check_input() {
+        let problems = check_input(
+            "Alice would gain 54 happiness units by sitting next to Bob.\n\
+             Alice would gain 1 happiness units by sitting next to Bob.\n\
+             Bob would gain 83 happiness units by sitting next to Carol.",
+        );
+        assert_eq!(
+            problems
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<String>>(),
+            vec![
+                "line 2: Alice next to Bob is already surveyed on line 1",
+                "input: happiness of Alice sitting next to Carol is not surveyed",
+                "input: happiness of Bob sitting next to Alice is not surveyed",
+                "input: happiness of Carol sitting next to Alice is not surveyed",
+                "input: happiness of Carol sitting next to Bob is not surveyed",
+            ]
+        );
+    }
 }
@@ -1,19 +1,30 @@
 use std::{collections::HashMap, error, io, str::FromStr};
 
+use code_2015::{
+    check::{check_lines, sort_by_line, InputProblem},
+    report::Visual,
+    trace,
+};
+
 fn main() -> io::Result<()> {
-    match io::stdin()
-        .lines()
-        .collect::<Result<Vec<String>, _>>()?
-        .iter()
-        .map(|l| l.parse::<ReindeerPerformance>())
-        .collect::<Result<Vec<ReindeerPerformance>, Box<dyn error::Error>>>()
-    {
+    trace::init();
+    match trace::parse(14, || {
+        io::stdin()
+            .lines()
+            .collect::<Result<Vec<String>, _>>()
+            .map(|lines| {
+                lines
+                    .iter()
+                    .map(|l| l.parse::<ReindeerPerformance>())
+                    .collect::<Result<Vec<ReindeerPerformance>, Box<dyn error::Error>>>()
+            })
+    })? {
         Ok(performances) => {
             let race = ReindeerRace { performances };
-            let ret = race.race(2503);
+            let ret = trace::solve(14, 1, || race.race(2503));
             println!("winning deer is {} and it's distance is {}", ret.0, ret.1);
 
-            let ret2 = race.race_mode_two(2503);
+            let ret2 = trace::solve(14, 2, || race.race_mode_two(2503));
             println!(
                 "winning deer for race mode two is {} and it's points is {}",
                 ret2.0, ret2.1
@@ -24,6 +35,58 @@ fn main() -> io::Result<()> {
     Ok(())
 }
 
+pub fn part_one(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(parse_race(input)?.race(2503).1.to_string())
+}
+
+pub fn part_two(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(parse_race(input)?.race_mode_two(2503).1.to_string())
+}
+
+pub fn visualize(input: &str) -> Result<Vec<Visual>, Box<dyn error::Error>> {
+    let race = parse_race(input)?;
+    let distances = race
+        .performances
+        .iter()
+        .map(|p| (p.name.clone(), (0..=2503).map(|t| p.distance(t)).collect()))
+        .collect();
+
+    let mut points = race
+        .performances
+        .iter()
+        .map(|p| (p.name.clone(), vec![0]))
+        .collect::<Vec<(String, Vec<u32>)>>();
+    for t in 1..2503 {
+        let winners = race.race_with_tie(t);
+        for (name, series) in points.iter_mut() {
+            let last = *series.last().unwrap();
+            if winners.iter().any(|w| w.0 == name) {
+                series.push(last + 1);
+            } else {
+                series.push(last);
+            }
+        }
+    }
+    Ok(vec![
+        Visual::Series {
+            title: "Distance over time".into(),
+            series: distances,
+        },
+        Visual::Series {
+            title: "Points over time".into(),
+            series: points,
+        },
+    ])
+}
+
+fn parse_race(input: &str) -> Result<ReindeerRace, Box<dyn error::Error>> {
+    let performances = input
+        .lines()
+        .map(|l| l.parse::<ReindeerPerformance>())
+        .collect::<Result<Vec<ReindeerPerformance>, _>>()?;
+    Ok(ReindeerRace { performances })
+}
+
 struct ReindeerPerformance {
     name: String,
     speed: u32,
@@ -31,6 +94,17 @@ struct ReindeerPerformance {
     full_cycle: u32,
 }
 
+impl ReindeerPerformance {
+    fn distance(&self, time: u32) -> u32 {
+        let full_distance = self.speed * self.stamina * (time / self.full_cycle);
+        let remain = time % self.full_cycle;
+        if remain > self.stamina {
+            return full_distance + self.speed * self.stamina;
+        }
+        full_distance + self.speed * remain
+    }
+}
+
 struct PointsCounter {
     points: u32,
 }
@@ -61,14 +135,7 @@ impl ReindeerRace {
         let mut r = self
             .performances
             .iter()
-            .map(|p| {
-                let full_distance = p.speed * p.stamina * (time / p.full_cycle);
-                let remain = time % p.full_cycle;
-                if remain > p.stamina {
-                    return (&p.name, full_distance + p.speed * p.stamina);
-                }
-                return (&p.name, full_distance + p.speed * remain);
-            })
+            .map(|p| (&p.name, p.distance(time)))
             .collect::<Vec<(&String, u32)>>();
         r.sort_by(|a, b| a.1.cmp(&b.1).reverse());
         r.iter()
@@ -96,6 +163,20 @@ impl ReindeerRace {
     }
 }
 
+pub fn check_input(input: &str) -> Vec<InputProblem> {
+    let (performances, mut problems) = check_lines::<ReindeerPerformance>(input);
+    for (line, p) in performances {
+        if p.full_cycle == 0 {
+            problems.push(InputProblem::at_line(
+                line,
+                format!("{} neither flies nor rests", p.name),
+            ));
+        }
+    }
+    sort_by_line(&mut problems);
+    problems
+}
+
 impl FromStr for ReindeerPerformance {
     type Err = Box<dyn error::Error>;
 
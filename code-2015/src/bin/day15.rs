@@ -1,18 +1,31 @@
 use std::{collections::HashMap, error, io, str::FromStr, vec};
 
+use code_2015::{
+    check::{check_lines, InputProblem},
+    trace,
+};
+
 fn main() -> io::Result<()> {
-    match io::stdin()
-        .lines()
-        .collect::<Result<Vec<String>, _>>()?
-        .iter()
-        .map(|l| l.parse::<Ingredients>())
-        .collect::<Result<Vec<Ingredients>, Box<dyn error::Error>>>()
-    {
+    trace::init();
+    match trace::parse(15, || {
+        io::stdin()
+            .lines()
+            .collect::<Result<Vec<String>, _>>()
+            .map(|lines| {
+                lines
+                    .iter()
+                    .map(|l| l.parse::<Ingredients>())
+                    .collect::<Result<Vec<Ingredients>, Box<dyn error::Error>>>()
+            })
+    })? {
         Ok(ingredients) => {
-            println!("highest score is {}", find_optimal(&ingredients, false));
+            println!(
+                "highest score is {}",
+                trace::solve(15, 1, || find_optimal(&ingredients, false))
+            );
             println!(
                 "highest score is with calories equals to 500 is {}",
-                find_optimal(&ingredients, true)
+                trace::solve(15, 2, || find_optimal(&ingredients, true))
             );
         }
         Err(e) => println!("parse input failed. {}", e),
@@ -20,6 +33,18 @@ fn main() -> io::Result<()> {
     Ok(())
 }
 
+pub fn part_one(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(find_optimal(&parse_ingredients(input)?, false).to_string())
+}
+
+pub fn part_two(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(find_optimal(&parse_ingredients(input)?, true).to_string())
+}
+
+fn parse_ingredients(input: &str) -> Result<Vec<Ingredients>, Box<dyn error::Error>> {
+    input.lines().map(|l| l.parse::<Ingredients>()).collect()
+}
+
 fn find_optimal(ingredients: &Vec<Ingredients>, filter_calories: bool) -> i64 {
     all_combinations(&ingredients, 100)
         .iter()
@@ -118,6 +143,10 @@ fn all_combinations(ingredients: &[Ingredients], remain_amount: u32) -> Vec<Hash
     out
 }
 
+pub fn check_input(input: &str) -> Vec<InputProblem> {
+    check_lines::<Ingredients>(input).1
+}
+
 #[derive(Debug)]
 struct Ingredients {
     name: String,
@@ -137,15 +166,17 @@ impl FromStr for Ingredients {
         )
         .map_err(|_|"invalid input format".into())
         .and_then(|re| {
-            let c = re.captures(s).unwrap();
+            let c = re
+                .captures(s)
+                .ok_or(format!("ingredient: \"{}\" is not in valid format", s))?;
             if c.len() < 7 {
                 return Err("invalid input format".into());
             }
-            let capacity = c[2].parse::<i64>().unwrap();
-            let durability = c[3].parse::<i64>().unwrap();
-            let flavor = c[4].parse::<i64>().unwrap();
-            let texture = c[5].parse::<i64>().unwrap();
-            let calories = c[6].parse::<i64>().unwrap();
+            let capacity = c[2].parse::<i64>()?;
+            let durability = c[3].parse::<i64>()?;
+            let flavor = c[4].parse::<i64>()?;
+            let texture = c[5].parse::<i64>()?;
+            let calories = c[6].parse::<i64>()?;
             return Ok(Ingredients{name: c[1].into(), capacity, durability, flavor, texture, calories})
         });
     }
@@ -1,66 +1,186 @@
-use std::{io, num::ParseIntError, str::FromStr};
+use std::{
+    env, error, fmt, fs,
+    io::{self, Read, Write},
+    num::ParseIntError,
+    str::FromStr,
+};
+
+use code_2015::{
+    check::{check_lines, sort_by_line, InputProblem},
+    trace,
+};
+use serde_json::Value;
 
 fn main() -> io::Result<()> {
-    let lines: Result<Vec<String>, _> = io::stdin().lines().collect();
-    match lines {
-        Ok(input) => {
-            let lines_str = &input.iter().map(|l| l.as_str()).collect();
-            match calculate_total_wrappers(lines_str) {
-                Ok(t) => println!("total square feet of wrapping paper: {}", t),
-                Err(e) => match e {
-                    CalculateError::InputFormatError(input) => {
-                        println!("invalid format for input string: \"{}\"", input)
-                    }
-                    CalculateError::ParseStringError(input, _) => {
-                        println!("parse number from input string: \"{}\" failed", input)
-                    }
-                },
-            };
-
-            match calculate_total_ribbons(lines_str) {
-                Ok(t) => println!("total ribbon: {}", t),
-                Err(e) => match e {
-                    CalculateError::InputFormatError(input) => {
-                        println!("invalid format for input string: \"{}\"", input)
-                    }
-                    CalculateError::ParseStringError(input, _) => {
-                        println!("parse number from input string: \"{}\" failed", input)
-                    }
-                },
-            };
+    trace::init();
+    let options = match Options::from_args(env::args().skip(1)) {
+        Ok(o) => o,
+        Err(e) => {
+            println!("{}", e);
+            println!(
+                "usage: day2 [--format <lines|csv|json>] [--report <file>] \
+                 [--slack <percent>] [--bow <feet>] [--roll <feet>]"
+            );
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, e));
+        }
+    };
+    let mut buffer = String::new();
+    if let Err(e) = trace::parse(2, || io::stdin().read_to_string(&mut buffer)) {
+        println!("read input lines faied, {}", e);
+        return Ok(());
+    }
+    let gifts = match trace::parse(2, || parse_manifest(buffer.as_str(), options.format)) {
+        Ok(g) => g,
+        Err(e) => {
+            println!("{}", e);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, e));
+        }
+    };
+
+    let totals = trace::solve(2, 1, || total_wrappers(&gifts)).and_then(|paper| {
+        println!("total square feet of wrapping paper: {}", paper);
+        let ribbon = trace::solve(2, 2, || total_ribbons(&gifts))?;
+        println!("total ribbon: {}", ribbon);
+        Ok(())
+    });
+    if let Err(e) = totals {
+        println!("{}", e);
+        return Err(io::Error::new(io::ErrorKind::InvalidData, e));
+    }
+    for strategy in options.strategies.iter() {
+        match total(&gifts, strategy.as_ref()) {
+            Ok(t) => println!("total {}: {}", strategy.name(), t),
+            Err(e) => println!("total {}: {}", strategy.name(), e),
         }
-        Err(e) => println!("read input lines faied, {}", e),
     }
 
+    match options.report.as_deref() {
+        Some("-") => write_report(io::stdout().lock(), &gifts)?,
+        Some(path) => write_report(fs::File::create(path)?, &gifts)?,
+        None => {}
+    }
     Ok(())
 }
 
+struct Options {
+    format: ManifestFormat,
+    report: Option<String>,
+    /// The rules added on the command line, on top of the elves' paper and
+    /// ribbon.
+    strategies: Vec<Box<dyn WrappingStrategy>>,
+}
+
+impl Options {
+    fn from_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options {
+            format: ManifestFormat::Lines,
+            report: None,
+            strategies: vec![],
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--format" => {
+                    let format = args.next().ok_or("missing format after --format")?;
+                    options.format = format.parse()?;
+                }
+                "--report" => {
+                    options.report = Some(args.next().ok_or("missing file after --report")?);
+                }
+                "--slack" => options.strategies.push(Box::new(Slack {
+                    base: Box::new(Paper),
+                    percent: positive(&arg, args.next())?,
+                })),
+                "--bow" => options.strategies.push(Box::new(FixedBow {
+                    bow: positive(&arg, args.next())?,
+                })),
+                "--roll" => options.strategies.push(Box::new(PaperRolls {
+                    width: positive(&arg, args.next())?,
+                })),
+                _ => return Err(format!("unknown option: \"{}\"", arg)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn positive(option: &str, value: Option<String>) -> Result<usize, String> {
+    let value = value.ok_or_else(|| format!("missing value after {}", option))?;
+    match value.parse::<usize>() {
+        Ok(v) if v > 0 => Ok(v),
+        _ => Err(format!("invalid value for {}: \"{}\"", option, value)),
+    }
+}
+
+pub fn part_one(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(calculate_total_wrappers(&input.lines().collect())?.to_string())
+}
+
+pub fn part_two(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(calculate_total_ribbons(&input.lines().collect())?.to_string())
+}
+
+#[derive(Debug, PartialEq)]
 struct Rectangular {
     length: usize,
     width: usize,
     height: usize,
 }
 
+/// The measures below return `None` when they don't fit in a `usize`.
 impl Rectangular {
-    fn calculate_wrapping_paper(&self) -> usize {
-        let a = self.length * self.width;
-        let b = self.length * self.height;
-        let c = self.width * self.height;
-        let r = c.min(a.min(b));
-        return 2 * (a + b + c) + r;
+    fn new(length: usize, width: usize, height: usize) -> Rectangular {
+        Rectangular {
+            length,
+            width,
+            height,
+        }
+    }
+
+    /// Rejects boxes with a zero-size dimension, they can't be wrapped.
+    fn wrappable(self, line: usize) -> Result<Rectangular, CalculateError> {
+        if self.length == 0 || self.width == 0 || self.height == 0 {
+            return Err(CalculateError::DegenerateBox(line, self.to_string()));
+        }
+        Ok(self)
+    }
+
+    fn calculate_wrapping_paper(&self) -> Option<usize> {
+        let a = self.length.checked_mul(self.width)?;
+        let b = self.length.checked_mul(self.height)?;
+        let c = self.width.checked_mul(self.height)?;
+        a.checked_add(b)?
+            .checked_add(c)?
+            .checked_mul(2)?
+            .checked_add(a.min(b).min(c))
+    }
+
+    fn calculate_ribbon(&self) -> Option<usize> {
+        self.volume()?.checked_add(self.smallest_perimeter()?)
+    }
+
+    fn smallest_side(&self) -> Option<usize> {
+        let mut edges = [self.length, self.height, self.width];
+        edges.sort();
+        edges[0].checked_mul(edges[1])
     }
 
-    fn calculate_ribbon(&self) -> usize {
-        let ribbon = self.length * self.height * self.width;
-        let mut edges = vec![self.length, self.height, self.width];
+    fn smallest_perimeter(&self) -> Option<usize> {
+        let mut edges = [self.length, self.height, self.width];
         edges.sort();
-        edges.reverse();
-        let r = edges[1..]
-            .into_iter()
-            .map(|e| *e)
-            .reduce(|total: usize, next: usize| -> usize { return total + next })
-            .unwrap_or(0);
-        return ribbon + 2 * r;
+        edges[0].checked_add(edges[1])?.checked_mul(2)
+    }
+
+    /// The bow takes as many feet of ribbon as the box has cubic feet.
+    fn volume(&self) -> Option<usize> {
+        self.length
+            .checked_mul(self.height)?
+            .checked_mul(self.width)
+    }
+}
+
+impl fmt::Display for Rectangular {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}x{}", self.length, self.width, self.height)
     }
 }
 
@@ -76,16 +196,13 @@ impl FromStr for Rectangular {
             .parse::<usize>()
             .and_then(|length| {
                 splitted[1].parse::<usize>().and_then(|width| {
-                    splitted[2].parse::<usize>().and_then(|height| {
-                        return Ok(Rectangular {
-                            length,
-                            width,
-                            height,
-                        });
-                    })
+                    splitted[2]
+                        .parse::<usize>()
+                        .map(|height| (length, width, height))
                 })
             })
-            .or_else(|e| return Err(CalculateError::ParseStringError(String::from(s), e)));
+            .map_err(|e| CalculateError::ParseStringError(String::from(s), e))
+            .map(|(length, width, height)| Rectangular::new(length, width, height));
     }
 }
 
@@ -93,26 +210,391 @@ impl FromStr for Rectangular {
 enum CalculateError {
     InputFormatError(String),
     ParseStringError(String, ParseIntError),
+    Manifest(String),
+    /// The box on the given line has a zero-size dimension.
+    DegenerateBox(usize, String),
+    /// The paper or ribbon of the box on the given line, or the total up to
+    /// it, doesn't fit.
+    Overflow(usize, String),
+}
+
+impl error::Error for CalculateError {}
+
+impl fmt::Display for CalculateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalculateError::InputFormatError(input) => {
+                write!(f, "invalid format for input string: \"{}\"", input)
+            }
+            CalculateError::ParseStringError(input, _) => {
+                write!(f, "parse number from input string: \"{}\" failed", input)
+            }
+            CalculateError::Manifest(message) => {
+                write!(f, "invalid gift manifest: {}", message)
+            }
+            CalculateError::DegenerateBox(line, input) => {
+                write!(
+                    f,
+                    "box \"{}\" on line {} has a zero-size dimension",
+                    input, line
+                )
+            }
+            CalculateError::Overflow(line, input) => {
+                write!(
+                    f,
+                    "box \"{}\" on line {} is too big to measure",
+                    input, line
+                )
+            }
+        }
+    }
 }
 
-fn calculate_total_wrappers(input: &Vec<&str>) -> Result<usize, CalculateError> {
-    let mut total_wrapper: usize = 0;
-    for line in input {
-        let rec = Rectangular::from_str(line)?;
-        total_wrapper += rec.calculate_wrapping_paper();
+fn calculate_total_wrappers(input: &Vec<&str>) -> Result<u128, CalculateError> {
+    let mut total_wrapper: u128 = 0;
+    for (i, line) in input.iter().enumerate() {
+        let rec = Rectangular::from_str(line)?.wrappable(i + 1)?;
+        let overflow = || CalculateError::Overflow(i + 1, line.to_string());
+        let paper = rec.calculate_wrapping_paper().ok_or_else(overflow)?;
+        total_wrapper = total_wrapper
+            .checked_add(paper as u128)
+            .ok_or_else(overflow)?;
     }
     return Ok(total_wrapper);
 }
 
-fn calculate_total_ribbons(input: &Vec<&str>) -> Result<usize, CalculateError> {
-    let mut total: usize = 0;
-    for line in input {
-        let rec = Rectangular::from_str(line)?;
-        total += rec.calculate_ribbon();
+fn calculate_total_ribbons(input: &Vec<&str>) -> Result<u128, CalculateError> {
+    let mut total: u128 = 0;
+    for (i, line) in input.iter().enumerate() {
+        let rec = Rectangular::from_str(line)?.wrappable(i + 1)?;
+        let overflow = || CalculateError::Overflow(i + 1, line.to_string());
+        let ribbon = rec.calculate_ribbon().ok_or_else(overflow)?;
+        total = total.checked_add(ribbon as u128).ok_or_else(overflow)?;
     }
     return Ok(total);
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ManifestFormat {
+    /// One `LxWxH` box per line.
+    Lines,
+    /// A header row naming the `name`, `length`, `width`, `height` and
+    /// optional `quantity` columns, then one gift per row.
+    Csv,
+    /// An array of objects with the same fields as the CSV columns.
+    Json,
+}
+
+impl FromStr for ManifestFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lines" => Ok(ManifestFormat::Lines),
+            "csv" => Ok(ManifestFormat::Csv),
+            "json" => Ok(ManifestFormat::Json),
+            _ => Err(format!("unknown manifest format: \"{}\"", s)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Gift {
+    name: String,
+    dimensions: Rectangular,
+    quantity: usize,
+    /// Where the gift is in the manifest: its line for line and CSV
+    /// manifests, its 1-based array index for JSON ones.
+    line: usize,
+}
+
+impl Gift {
+    fn measure(
+        &self,
+        measure: impl Fn(&Rectangular) -> Option<usize>,
+    ) -> Result<usize, CalculateError> {
+        measure(&self.dimensions)
+            .ok_or_else(|| CalculateError::Overflow(self.line, self.dimensions.to_string()))
+    }
+}
+
+fn parse_manifest(input: &str, format: ManifestFormat) -> Result<Vec<Gift>, CalculateError> {
+    match format {
+        ManifestFormat::Lines => parse_lines_manifest(input),
+        ManifestFormat::Csv => parse_csv_manifest(input),
+        ManifestFormat::Json => parse_json_manifest(input),
+    }
+}
+
+fn parse_lines_manifest(input: &str) -> Result<Vec<Gift>, CalculateError> {
+    let mut gifts = vec![];
+    for (i, line) in input.lines().enumerate() {
+        gifts.push(Gift {
+            name: format!("line {}", i + 1),
+            dimensions: Rectangular::from_str(line)?.wrappable(i + 1)?,
+            quantity: 1,
+            line: i + 1,
+        });
+    }
+    Ok(gifts)
+}
+
+fn parse_csv_manifest(input: &str) -> Result<Vec<Gift>, CalculateError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(input.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|e| CalculateError::Manifest(e.to_string()))?
+        .clone();
+    let column = |name: &str| headers.iter().position(|h| h.eq_ignore_ascii_case(name));
+    let required = |name: &str| {
+        column(name).ok_or_else(|| CalculateError::Manifest(format!("missing column \"{}\"", name)))
+    };
+    let (length, width, height) = (required("length")?, required("width")?, required("height")?);
+    let (name, quantity) = (column("name"), column("quantity"));
+
+    let mut gifts = vec![];
+    for (i, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| CalculateError::Manifest(e.to_string()))?;
+        // The header is line 1, so without quoted line breaks the first gift
+        // sits on line 2.
+        let line = record.position().map_or(i + 2, |p| p.line() as usize);
+        let field = |column: usize| record.get(column).unwrap_or("");
+        let number = |column: usize, default: Option<usize>| {
+            let value = field(column);
+            if value.is_empty() {
+                if let Some(d) = default {
+                    return Ok(d);
+                }
+            }
+            value.parse::<usize>().map_err(|_| {
+                CalculateError::Manifest(format!(
+                    "line {}: invalid {} \"{}\"",
+                    line, &headers[column], value
+                ))
+            })
+        };
+        gifts.push(Gift {
+            name: name
+                .map(|c| field(c).to_string())
+                .filter(|n| !n.is_empty())
+                .unwrap_or_else(|| format!("gift {}", i + 1)),
+            dimensions: Rectangular::new(
+                number(length, None)?,
+                number(width, None)?,
+                number(height, None)?,
+            )
+            .wrappable(line)?,
+            quantity: match quantity {
+                Some(c) => number(c, Some(1))?,
+                None => 1,
+            },
+            line,
+        });
+    }
+    Ok(gifts)
+}
+
+fn parse_json_manifest(input: &str) -> Result<Vec<Gift>, CalculateError> {
+    let value: Value =
+        serde_json::from_str(input).map_err(|e| CalculateError::Manifest(e.to_string()))?;
+    let entries = value
+        .as_array()
+        .ok_or_else(|| CalculateError::Manifest("expected an array of gifts".to_string()))?;
+
+    let mut gifts = vec![];
+    for (i, entry) in entries.iter().enumerate() {
+        let number = |key: &str, default: Option<usize>| match entry.get(key) {
+            None if default.is_some() => Ok(default.unwrap()),
+            Some(v) if v.as_u64().is_some() => Ok(v.as_u64().unwrap() as usize),
+            _ => Err(CalculateError::Manifest(format!(
+                "gift {}: missing or invalid \"{}\"",
+                i + 1,
+                key
+            ))),
+        };
+        gifts.push(Gift {
+            name: match entry.get("name").and_then(|n| n.as_str()) {
+                Some(n) => n.to_string(),
+                None => format!("gift {}", i + 1),
+            },
+            dimensions: Rectangular::new(
+                number("length", None)?,
+                number("width", None)?,
+                number("height", None)?,
+            )
+            .wrappable(i + 1)?,
+            quantity: number("quantity", Some(1))?,
+            line: i + 1,
+        });
+    }
+    Ok(gifts)
+}
+
+/// A rule for how much paper or ribbon one box needs.
+trait WrappingStrategy {
+    /// What is measured, e.g. `square feet of wrapping paper`.
+    fn name(&self) -> String;
+
+    /// The amount for one box, `None` when it doesn't fit in a `usize`.
+    fn amount(&self, rec: &Rectangular) -> Option<usize>;
+}
+
+/// The elves' paper rule: the surface area plus the smallest side.
+struct Paper;
+
+impl WrappingStrategy for Paper {
+    fn name(&self) -> String {
+        "square feet of wrapping paper".into()
+    }
+
+    fn amount(&self, rec: &Rectangular) -> Option<usize> {
+        rec.calculate_wrapping_paper()
+    }
+}
+
+/// The elves' ribbon rule: the smallest perimeter plus the volume for the bow.
+struct Ribbon;
+
+impl WrappingStrategy for Ribbon {
+    fn name(&self) -> String {
+        "feet of ribbon".into()
+    }
+
+    fn amount(&self, rec: &Rectangular) -> Option<usize> {
+        rec.calculate_ribbon()
+    }
+}
+
+/// Adds a percentage on top of another rule, rounded up.
+struct Slack {
+    base: Box<dyn WrappingStrategy>,
+    percent: usize,
+}
+
+impl WrappingStrategy for Slack {
+    fn name(&self) -> String {
+        format!("{} with {}% slack", self.base.name(), self.percent)
+    }
+
+    fn amount(&self, rec: &Rectangular) -> Option<usize> {
+        let amount = self.base.amount(rec)?;
+        let slack = amount.checked_mul(self.percent)?.div_ceil(100);
+        amount.checked_add(slack)
+    }
+}
+
+/// Ribbon around the smallest perimeter with a bow of the same length on
+/// every box.
+struct FixedBow {
+    bow: usize,
+}
+
+impl WrappingStrategy for FixedBow {
+    fn name(&self) -> String {
+        format!("feet of ribbon with {} feet bows", self.bow)
+    }
+
+    fn amount(&self, rec: &Rectangular) -> Option<usize> {
+        rec.smallest_perimeter()?.checked_add(self.bow)
+    }
+}
+
+/// Paper sold by the foot from rolls `width` feet wide. Each box needs a whole
+/// number of feet, so the leftover of its last foot is wasted.
+struct PaperRolls {
+    width: usize,
+}
+
+impl WrappingStrategy for PaperRolls {
+    fn name(&self) -> String {
+        format!(
+            "square feet of wrapping paper from {} feet wide rolls",
+            self.width
+        )
+    }
+
+    fn amount(&self, rec: &Rectangular) -> Option<usize> {
+        let paper = rec.calculate_wrapping_paper()?;
+        paper.div_ceil(self.width).checked_mul(self.width)
+    }
+}
+
+fn total_wrappers(gifts: &[Gift]) -> Result<u128, CalculateError> {
+    total(gifts, &Paper)
+}
+
+fn total_ribbons(gifts: &[Gift]) -> Result<u128, CalculateError> {
+    total(gifts, &Ribbon)
+}
+
+// A `usize` amount times a `usize` quantity always fits in a `u128`, only the
+// sum over the gifts needs checking.
+fn total(gifts: &[Gift], strategy: &dyn WrappingStrategy) -> Result<u128, CalculateError> {
+    let mut total: u128 = 0;
+    for g in gifts {
+        let amount = g.measure(|rec| strategy.amount(rec))? as u128 * g.quantity as u128;
+        total = total
+            .checked_add(amount)
+            .ok_or_else(|| CalculateError::Overflow(g.line, g.dimensions.to_string()))?;
+    }
+    Ok(total)
+}
+
+/// Writes one CSV row per gift. The per-box columns are for a single box,
+/// the `total_` columns are multiplied by the quantity.
+fn write_report(writer: impl Write, gifts: &[Gift]) -> io::Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record([
+        "name",
+        "quantity",
+        "length",
+        "width",
+        "height",
+        "paper",
+        "smallest_side",
+        "ribbon",
+        "bow",
+        "total_paper",
+        "total_ribbon",
+    ])?;
+    for g in gifts {
+        let d = &g.dimensions;
+        let measure = |m: fn(&Rectangular) -> Option<usize>| {
+            g.measure(m)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        };
+        let paper = measure(Rectangular::calculate_wrapping_paper)?;
+        let ribbon = measure(Rectangular::calculate_ribbon)?;
+        writer.write_record([
+            g.name.clone(),
+            g.quantity.to_string(),
+            d.length.to_string(),
+            d.width.to_string(),
+            d.height.to_string(),
+            paper.to_string(),
+            measure(Rectangular::smallest_side)?.to_string(),
+            measure(Rectangular::smallest_perimeter)?.to_string(),
+            measure(Rectangular::volume)?.to_string(),
+            (paper as u128 * g.quantity as u128).to_string(),
+            (ribbon as u128 * g.quantity as u128).to_string(),
+        ])?;
+    }
+    writer.flush()
+}
+
+pub fn check_input(input: &str) -> Vec<InputProblem> {
+    let (boxes, mut problems) = check_lines::<Rectangular>(input);
+    for (line, rec) in boxes {
+        if let Err(e) = rec.wrappable(line) {
+            problems.push(InputProblem::at_line(line, e.to_string()));
+        }
+    }
+    sort_by_line(&mut problems);
+    problems
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -144,4 +626,135 @@ mod tests {
         assert_eq!(calculate_total_ribbons(&vec!["2x3x4"]).unwrap(), 34);
         assert_eq!(calculate_total_ribbons(&vec!["1x1x10"]).unwrap(), 14);
     }
+    #[test]
+    fn test_manifest_formats() {
+        let lines = parse_manifest("2x3x4\n1x1x10\n1x1x10\n", ManifestFormat::Lines).unwrap();
+        let csv = "name, length, width, height, quantity\n\
+                   train,2,3,4,\n\
+                   \"socks, wool\",1,1,10,2\n";
+        let csv = parse_manifest(csv, ManifestFormat::Csv).unwrap();
+        let json = r#"[
+            {"name": "train", "length": 2, "width": 3, "height": 4},
+            {"name": "socks, wool", "length": 1, "width": 1, "height": 10, "quantity": 2}
+        ]"#;
+        let json = parse_manifest(json, ManifestFormat::Json).unwrap();
+
+        let key = |g: &Gift| (g.name.clone(), g.dimensions.to_string(), g.quantity);
+        assert_eq!(
+            csv.iter().map(key).collect::<Vec<_>>(),
+            json.iter().map(key).collect::<Vec<_>>()
+        );
+        assert_eq!(csv[1].name, "socks, wool");
+        assert_eq!(csv[1].quantity, 2);
+        assert_eq!((csv[1].line, json[1].line), (3, 2));
+        for gifts in [&lines, &csv] {
+            assert_eq!(total_wrappers(gifts), Ok(58 + 43 * 2));
+            assert_eq!(total_ribbons(gifts), Ok(34 + 14 * 2));
+        }
+    }
+    #[test]
+    fn test_invalid_manifest() {
+        assert_eq!(
+            parse_manifest("name,length,width\na,1,2\n", ManifestFormat::Csv).unwrap_err(),
+            CalculateError::Manifest("missing column \"height\"".into())
+        );
+        assert_eq!(
+            parse_manifest("length,width,height\n1,2,x\n", ManifestFormat::Csv).unwrap_err(),
+            CalculateError::Manifest("line 2: invalid height \"x\"".into())
+        );
+        assert_eq!(
+            parse_manifest(r#"[{"length": 1, "width": -2}]"#, ManifestFormat::Json).unwrap_err(),
+            CalculateError::Manifest("gift 1: missing or invalid \"width\"".into())
+        );
+        assert!(parse_manifest("{}", ManifestFormat::Json).is_err());
+    }
+    #[test]
+    fn test_degenerate_box() {
+        assert_eq!(
+            calculate_total_wrappers(&vec!["2x3x4", "2x0x4"]).unwrap_err(),
+            CalculateError::DegenerateBox(2, "2x0x4".into())
+        );
+        assert_eq!(
+            parse_manifest(
+                r#"[{"length": 0, "width": 1, "height": 1}]"#,
+                ManifestFormat::Json
+            )
+            .unwrap_err(),
+            CalculateError::DegenerateBox(1, "0x1x1".into())
+        );
+        assert_eq!(
+            parse_manifest("1x1x1\n1x0x1\n", ManifestFormat::Lines).unwrap_err(),
+            CalculateError::DegenerateBox(2, "1x0x1".into())
+        );
+        let problems = check_input("1x1x1\n1x0x1\n");
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].line, Some(2));
+    }
+    #[test]
+    fn test_overflow() {
+        let huge = format!("{}x{}x1", 1usize << 32, 1usize << 32);
+        assert_eq!(
+            calculate_total_wrappers(&vec!["2x3x4", huge.as_str()]).unwrap_err(),
+            CalculateError::Overflow(2, huge.clone())
+        );
+        assert_eq!(
+            calculate_total_ribbons(&vec![huge.as_str()]).unwrap_err(),
+            CalculateError::Overflow(1, huge.clone())
+        );
+
+        // Totals beyond `usize::MAX` are still exact.
+        let gifts = parse_manifest(
+            &format!(
+                "length,width,height,quantity\n2,3,4,{}\n1,1,10,{}\n",
+                usize::MAX,
+                usize::MAX
+            ),
+            ManifestFormat::Csv,
+        )
+        .unwrap();
+        assert_eq!(total_wrappers(&gifts), Ok((58 + 43) * usize::MAX as u128));
+        assert_eq!(total_ribbons(&gifts), Ok((34 + 14) * usize::MAX as u128));
+    }
+    #[test]
+    fn test_strategies() {
+        let rec = Rectangular::from_str("2x3x4").unwrap();
+        let slack = Slack {
+            base: Box::new(Paper),
+            percent: 10,
+        };
+        assert_eq!(Paper.amount(&rec), Some(58));
+        assert_eq!(Ribbon.amount(&rec), Some(34));
+        assert_eq!(slack.amount(&rec), Some(64));
+        assert_eq!(FixedBow { bow: 3 }.amount(&rec), Some(13));
+        assert_eq!(PaperRolls { width: 4 }.amount(&rec), Some(60));
+        assert_eq!(PaperRolls { width: 2 }.amount(&rec), Some(58));
+        assert_eq!(slack.name(), "square feet of wrapping paper with 10% slack");
+
+        let gifts = parse_manifest("2x3x4\n1x1x10\n", ManifestFormat::Lines).unwrap();
+        assert_eq!(total(&gifts, &Paper), total_wrappers(&gifts));
+        assert_eq!(total(&gifts, &slack), Ok(64 + 48));
+        assert_eq!(total(&gifts, &FixedBow { bow: 3 }), Ok(13 + 7));
+
+        assert_eq!(FixedBow { bow: usize::MAX }.amount(&rec), None);
+    }
+    #[test]
+    fn test_write_report() {
+        let gifts = vec![Gift {
+            name: "train".into(),
+            dimensions: Rectangular {
+                length: 2,
+                width: 3,
+                height: 4,
+            },
+            quantity: 3,
+            line: 1,
+        }];
+        let mut out = vec![];
+        write_report(&mut out, &gifts).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "name,quantity,length,width,height,paper,smallest_side,ribbon,bow,total_paper,total_ribbon\n\
+             train,3,2,3,4,58,6,10,24,174,102\n"
+        );
+    }
 }
@@ -1,14 +1,107 @@
-use std::{collections::HashSet, io};
+use std::{
+    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet},
+    env, error, fmt, fs, io,
+    str::FromStr,
+};
+
+use code_2015::{
+    check::{check_single_line, InputProblem},
+    report::Visual,
+    trace,
+};
 
 fn main() -> io::Result<()> {
+    trace::init();
+    let options = match Options::from_args(env::args().skip(1)) {
+        Ok(o) => o,
+        Err(e) => {
+            println!("{}", e);
+            println!(
+                "usage: day3 [--santas <n>] [--dispatch <policy>] [--histogram] \
+                 [--at-least <k>]... [--ascii <file>] [--heatmap <file.ppm>] \
+                 [--position <step>]... [--first-visit <x>,<y>]... [--exceeds <houses>]... \
+                 [--alphabet <alphabet>]"
+            );
+            println!("       policy: round-robin, chunked:<size> or weighted:<w1>,<w2>,...");
+            println!(
+                "       alphabet: arrows, eight-way, hex or <char>=<dx>,<dy> entries \
+                 separated by spaces"
+            );
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, e));
+        }
+    };
     let mut buffer = String::new();
-    let read_ret = io::stdin().read_line(&mut buffer);
+    let read_ret = trace::parse(3, || io::stdin().read_line(&mut buffer));
     match read_ret {
         Ok(_) => {
-            let ret = spread_presents(buffer.as_str());
+            let ret = trace::solve(3, 1, || spread_presents(buffer.as_str()));
             println!("Santa visited {} houses", ret);
-            let ret = spread_presents_with_robot(buffer.as_str());
+            let ret = trace::solve(3, 2, || spread_presents_with_robot(buffer.as_str()));
             println!("Santa and Robot-Santa visited {} houses", ret);
+            if let Some(actors) = options.santas {
+                let input = buffer.trim_end();
+                let delivery = match spread_presents_with_actors(
+                    input,
+                    actors,
+                    &options.dispatch,
+                    &options.alphabet,
+                ) {
+                    Ok(d) => d,
+                    Err(e) => {
+                        println!("{}", e);
+                        return Err(io::Error::new(io::ErrorKind::InvalidInput, e));
+                    }
+                };
+                println!("{} santas visited {} houses", actors, delivery.union);
+                for (i, visited) in delivery.per_actor.iter().enumerate() {
+                    println!("santa {} visited {} houses", i + 1, visited.len());
+                }
+            }
+            if options.wants_visits() {
+                let input = buffer.trim_end();
+                let actors = options.santas.unwrap_or(1);
+                let visits = match count_visits(input, actors, &options.dispatch, &options.alphabet)
+                {
+                    Ok(v) => v,
+                    Err(e) => {
+                        println!("{}", e);
+                        return Err(io::Error::new(io::ErrorKind::InvalidInput, e));
+                    }
+                };
+                if options.histogram {
+                    for (presents, houses) in presents_histogram(&visits) {
+                        println!("{} houses got {} presents", houses, presents);
+                    }
+                }
+                for k in options.at_least.iter() {
+                    let houses = visits.values().filter(|v| v.presents >= *k).count();
+                    println!("{} houses got at least {} presents", houses, k);
+                }
+                let map_error = |e: MapTooLarge| {
+                    println!("{}", e);
+                    io::Error::new(io::ErrorKind::InvalidInput, e)
+                };
+                match options.ascii.as_deref() {
+                    Some("-") => print!("{}", render_ascii(&visits).map_err(map_error)?),
+                    Some(path) => fs::write(path, render_ascii(&visits).map_err(map_error)?)?,
+                    None => {}
+                }
+                if let Some(path) = options.heatmap.as_deref() {
+                    fs::write(path, render_ppm(&visits).map_err(map_error)?)?;
+                }
+            }
+            if !options.queries.is_empty() {
+                let replay = match Replay::new(buffer.trim_end(), &options.alphabet) {
+                    Ok(r) => r,
+                    Err(e) => {
+                        println!("{}", e);
+                        return Err(io::Error::new(io::ErrorKind::InvalidInput, e));
+                    }
+                };
+                for query in options.queries.iter() {
+                    println!("{}", query.answer(&replay));
+                }
+            }
             return Ok(());
         }
         Err(err) => {
@@ -18,50 +111,656 @@ fn main() -> io::Result<()> {
     }
 }
 
-fn spread_presents(input: &str) -> usize {
-    if input.is_empty() {
-        return 0;
+struct Options {
+    santas: Option<usize>,
+    dispatch: Dispatch,
+    histogram: bool,
+    at_least: Vec<usize>,
+    ascii: Option<String>,
+    heatmap: Option<String>,
+    queries: Vec<Query>,
+    alphabet: Alphabet,
+}
+
+impl Options {
+    fn from_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options {
+            santas: None,
+            dispatch: Dispatch::RoundRobin,
+            histogram: false,
+            at_least: vec![],
+            ascii: None,
+            heatmap: None,
+            queries: vec![],
+            alphabet: Alphabet::arrows(),
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--santas" => {
+                    let n = args.next().ok_or("missing count after --santas")?;
+                    options.santas = Some(
+                        n.parse::<usize>()
+                            .map_err(|_| format!("invalid santa count: \"{}\"", n))?,
+                    );
+                }
+                "--dispatch" => {
+                    let policy = args.next().ok_or("missing policy after --dispatch")?;
+                    options.dispatch = policy.parse()?;
+                }
+                "--histogram" => options.histogram = true,
+                "--at-least" => {
+                    let k = args.next().ok_or("missing count after --at-least")?;
+                    options.at_least.push(
+                        k.parse::<usize>()
+                            .map_err(|_| format!("invalid present count: \"{}\"", k))?,
+                    );
+                }
+                "--ascii" => options.ascii = Some(args.next().ok_or("missing file after --ascii")?),
+                "--heatmap" => {
+                    options.heatmap = Some(args.next().ok_or("missing file after --heatmap")?)
+                }
+                "--position" | "--first-visit" | "--exceeds" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("missing value after {}", arg))?;
+                    options.queries.push(Query::parse(&arg, &value)?);
+                }
+                "--alphabet" => {
+                    let alphabet = args.next().ok_or("missing alphabet after --alphabet")?;
+                    options.alphabet = alphabet.parse()?;
+                }
+                _ => return Err(format!("unknown option: \"{}\"", arg)),
+            }
+        }
+        if options.santas.is_none() && options.dispatch != Dispatch::RoundRobin {
+            return Err("--dispatch needs --santas".into());
+        }
+        Ok(options)
     }
 
-    let mut pos = (0, 0);
-    let mut visited: HashSet<(i32, i32)> = HashSet::new();
-    visited.insert(pos);
-    for direct in input.chars() {
-        pos = next_pos(direct, pos);
-        visited.insert(pos);
+    fn wants_visits(&self) -> bool {
+        self.histogram
+            || !self.at_least.is_empty()
+            || self.ascii.is_some()
+            || self.heatmap.is_some()
     }
-    return visited.len();
+}
+
+pub fn part_one(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(spread_presents(input.trim_end()).to_string())
+}
+
+pub fn part_two(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(spread_presents_with_robot(input.trim_end()).to_string())
+}
+
+pub fn visualize(input: &str) -> Result<Vec<Visual>, Box<dyn error::Error>> {
+    let alphabet = Alphabet::arrows().lenient();
+    let visits = count_visits(input.trim_end(), 1, &Dispatch::RoundRobin, &alphabet)?;
+    let grid = VisitGrid::new(&visits)?;
+    Ok(vec![Visual::Grid {
+        title: "Presents per house delivered by Santa".into(),
+        width: grid.width,
+        height: grid.height,
+        values: grid.presents.iter().map(|p| *p as u32).collect(),
+    }])
+}
+
+fn spread_presents(input: &str) -> usize {
+    let alphabet = Alphabet::arrows().lenient();
+    spread_presents_with_actors(input, 1, &Dispatch::RoundRobin, &alphabet)
+        .unwrap()
+        .union
 }
 
 fn spread_presents_with_robot(input: &str) -> usize {
+    let alphabet = Alphabet::arrows().lenient();
+    spread_presents_with_actors(input, 2, &Dispatch::RoundRobin, &alphabet)
+        .unwrap()
+        .union
+}
+
+/// Decides which deliverer follows each instruction. Every policy repeats a
+/// fixed round of turns.
+#[derive(Debug, Clone, PartialEq)]
+enum Dispatch {
+    /// One instruction per deliverer in turn.
+    RoundRobin,
+    /// A block of this many instructions per deliverer in turn.
+    Chunked(usize),
+    /// A block of `weights[i]` instructions for deliverer `i` in turn. A zero
+    /// weight leaves that deliverer at the starting house.
+    Weighted(Vec<usize>),
+}
+
+impl Dispatch {
+    /// Checks the policy against the number of deliverers.
+    fn turns(&self, actors: usize) -> Result<Turns, DeliveryError> {
+        if actors == 0 {
+            return Err(DeliveryError::NoActors);
+        }
+        match self {
+            Dispatch::RoundRobin => Ok(Turns::Chunked { size: 1, actors }),
+            Dispatch::Chunked(0) => Err(DeliveryError::EmptyRound),
+            Dispatch::Chunked(size) => Ok(Turns::Chunked {
+                size: *size,
+                actors,
+            }),
+            Dispatch::Weighted(weights) if weights.len() != actors => {
+                Err(DeliveryError::WeightCount(actors, weights.len()))
+            }
+            Dispatch::Weighted(weights) => {
+                let ends = weights
+                    .iter()
+                    .scan(0usize, |end, w| {
+                        *end = end.saturating_add(*w);
+                        Some(*end)
+                    })
+                    .collect::<Vec<_>>();
+                match ends.last() {
+                    Some(0) | None => Err(DeliveryError::EmptyRound),
+                    Some(_) => Ok(Turns::Shares(ends)),
+                }
+            }
+        }
+    }
+}
+
+/// The deliverer of each instruction under a checked policy, worked out
+/// from the step number rather than a listed round.
+enum Turns {
+    /// Every deliverer follows `size` instructions in a row.
+    Chunked { size: usize, actors: usize },
+    /// Where each deliverer's share of a round ends.
+    Shares(Vec<usize>),
+}
+
+impl Turns {
+    fn actor(&self, step: usize) -> usize {
+        match self {
+            Turns::Chunked { size, actors } => step / size % actors,
+            Turns::Shares(ends) => {
+                let offset = step % ends[ends.len() - 1];
+                ends.partition_point(|end| *end <= offset)
+            }
+        }
+    }
+}
+
+impl FromStr for Dispatch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid dispatch policy: \"{}\"", s);
+        match s.split_once(':') {
+            None if s == "round-robin" => Ok(Dispatch::RoundRobin),
+            Some(("chunked", size)) => Ok(Dispatch::Chunked(size.parse().map_err(|_| invalid())?)),
+            Some(("weighted", weights)) => Ok(Dispatch::Weighted(
+                weights
+                    .split(',')
+                    .map(|w| w.parse::<usize>())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| invalid())?,
+            )),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum DeliveryError {
+    NoActors,
+    /// A weighted policy for this many deliverers got this many weights.
+    WeightCount(usize, usize),
+    /// The policy never hands out an instruction.
+    EmptyRound,
+    /// The alphabet has no move for the character at this offset.
+    UnmappedCharacter(usize, char),
+    /// The move at this offset leads past the largest coordinate.
+    OutOfBounds(usize),
+}
+
+impl error::Error for DeliveryError {}
+
+impl fmt::Display for DeliveryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeliveryError::NoActors => write!(f, "at least one deliverer is needed"),
+            DeliveryError::WeightCount(actors, weights) => {
+                write!(
+                    f,
+                    "{} deliverers need {} weights, got {}",
+                    actors, actors, weights
+                )
+            }
+            DeliveryError::EmptyRound => write!(f, "the dispatch policy hands out no instruction"),
+            DeliveryError::UnmappedCharacter(offset, c) => {
+                write!(f, "no move for character {:?} at offset: {}", c, offset)
+            }
+            DeliveryError::OutOfBounds(offset) => {
+                write!(f, "the move at offset: {} leaves the map", offset)
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Delivery {
+    /// The houses each deliverer visited, the starting house included.
+    per_actor: Vec<HashSet<(i32, i32)>>,
+    /// How many houses got at least one present.
+    union: usize,
+}
+
+/// Spreads the instructions over `actors` deliverers that all start at the
+/// same house. Nobody delivers anything when there are no instructions.
+fn spread_presents_with_actors(
+    input: &str,
+    actors: usize,
+    dispatch: &Dispatch,
+    alphabet: &Alphabet,
+) -> Result<Delivery, DeliveryError> {
+    let mut per_actor = vec![HashSet::new(); actors];
+    deliver(input, actors, dispatch, alphabet, |_, actor, house| {
+        per_actor[actor].insert(house);
+    })?;
+    let union = per_actor.iter().flatten().collect::<HashSet<_>>().len();
+    Ok(Delivery { per_actor, union })
+}
+
+/// Calls `visit(step, actor, house)` for every present delivered. Each
+/// deliverer drops one at the starting house at step 0, then one after every
+/// instruction it follows, with steps counted from 1. Nothing is delivered
+/// when there are no instructions.
+fn deliver(
+    input: &str,
+    actors: usize,
+    dispatch: &Dispatch,
+    alphabet: &Alphabet,
+    mut visit: impl FnMut(usize, usize, (i32, i32)),
+) -> Result<(), DeliveryError> {
+    let turns = dispatch.turns(actors)?;
     if input.is_empty() {
-        return 0;
+        return Ok(());
+    }
+
+    let mut positions = vec![(0, 0); actors];
+    for actor in 0..actors {
+        visit(0, actor, (0, 0));
+    }
+    for (step, direct) in input.chars().enumerate() {
+        let actor = turns.actor(step);
+        positions[actor] = alphabet.next_pos(step, direct, positions[actor])?;
+        visit(step + 1, actor, positions[actor]);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct HouseVisits {
+    presents: usize,
+    /// The step of the first present, see `deliver`.
+    first_step: usize,
+    /// Who delivered the first present.
+    first_actor: usize,
+}
+
+fn count_visits(
+    input: &str,
+    actors: usize,
+    dispatch: &Dispatch,
+    alphabet: &Alphabet,
+) -> Result<HashMap<(i32, i32), HouseVisits>, DeliveryError> {
+    let mut visits: HashMap<(i32, i32), HouseVisits> = HashMap::new();
+    deliver(input, actors, dispatch, alphabet, |step, actor, house| {
+        visits
+            .entry(house)
+            .or_insert(HouseVisits {
+                presents: 0,
+                first_step: step,
+                first_actor: actor,
+            })
+            .presents += 1;
+    })?;
+    Ok(visits)
+}
+
+/// How many houses got each number of presents.
+fn presents_histogram(visits: &HashMap<(i32, i32), HouseVisits>) -> BTreeMap<usize, usize> {
+    let mut histogram = BTreeMap::new();
+    for v in visits.values() {
+        *histogram.entry(v.presents).or_insert(0) += 1;
     }
+    histogram
+}
+
+/// The most houses a map is drawn with, one byte each in ASCII and three in
+/// a heatmap.
+const MAX_MAP_HOUSES: u64 = 1 << 24;
+
+/// The houses are too far apart to draw one character or pixel per house.
+#[derive(Debug, PartialEq)]
+struct MapTooLarge {
+    width: u64,
+    height: u64,
+}
+
+impl error::Error for MapTooLarge {}
+
+impl fmt::Display for MapTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "a {}x{} map is larger than the limit of {} houses",
+            self.width, self.height, MAX_MAP_HOUSES
+        )
+    }
+}
+
+/// The smallest rectangle holding every visited house, as a row-major grid of
+/// present counts with the northmost row first.
+struct VisitGrid {
+    width: usize,
+    height: usize,
+    presents: Vec<usize>,
+}
+
+impl VisitGrid {
+    fn new(visits: &HashMap<(i32, i32), HouseVisits>) -> Result<VisitGrid, MapTooLarge> {
+        if visits.is_empty() {
+            return Ok(VisitGrid {
+                width: 0,
+                height: 0,
+                presents: vec![],
+            });
+        }
+        let min_x = visits.keys().map(|h| h.0).min().unwrap();
+        let max_x = visits.keys().map(|h| h.0).max().unwrap();
+        let min_y = visits.keys().map(|h| h.1).min().unwrap();
+        let max_y = visits.keys().map(|h| h.1).max().unwrap();
+        // Spans of i32 coordinates always fit in an i64.
+        let width = (i64::from(max_x) - i64::from(min_x) + 1) as u64;
+        let height = (i64::from(max_y) - i64::from(min_y) + 1) as u64;
+        if width
+            .checked_mul(height)
+            .is_none_or(|houses| houses > MAX_MAP_HOUSES)
+        {
+            return Err(MapTooLarge { width, height });
+        }
+        let (width, height) = (width as usize, height as usize);
+        let mut presents = vec![0; width * height];
+        for (house, v) in visits {
+            let row = (i64::from(max_y) - i64::from(house.1)) as usize;
+            let column = (i64::from(house.0) - i64::from(min_x)) as usize;
+            presents[row * width + column] = v.presents;
+        }
+        Ok(VisitGrid {
+            width,
+            height,
+            presents,
+        })
+    }
+}
+
+/// Draws one character per house: a space for no present, the count for up
+/// to nine presents and `#` for more.
+fn render_ascii(visits: &HashMap<(i32, i32), HouseVisits>) -> Result<String, MapTooLarge> {
+    let grid = VisitGrid::new(visits)?;
+    let mut out = String::with_capacity((grid.width + 1) * grid.height);
+    for row in grid.presents.chunks(grid.width.max(1)) {
+        for p in row {
+            out.push(match p {
+                0 => ' ',
+                1..=9 => char::from_digit(*p as u32, 10).unwrap(),
+                _ => '#',
+            });
+        }
+        out.push('\n');
+    }
+    Ok(out)
+}
 
-    let mut visited: HashSet<(i32, i32)> = HashSet::new();
-    visited.insert((0, 0));
-    for actor in 0..2 {
+/// Draws a binary PPM image with one pixel per house, from black for no
+/// present through red to yellow for the most presents. The scale is
+/// logarithmic so that houses with a single present stay visible.
+fn render_ppm(visits: &HashMap<(i32, i32), HouseVisits>) -> Result<Vec<u8>, MapTooLarge> {
+    let grid = VisitGrid::new(visits)?;
+    let max = (grid.presents.iter().max().cloned().unwrap_or(0) as f64).ln_1p();
+    let mut out = format!("P6\n{} {}\n255\n", grid.width, grid.height).into_bytes();
+    for p in grid.presents {
+        let heat = if p == 0 {
+            0.0
+        } else {
+            // A lone present is a dim red even when it's also the maximum.
+            0.25 + 0.75 * (p as f64).ln_1p() / max.max(2f64.ln())
+        };
+        out.push(((heat * 2.0).min(1.0) * 255.0) as u8);
+        out.push(((heat * 2.0 - 1.0).max(0.0) * 255.0) as u8);
+        out.push(0);
+    }
+    Ok(out)
+}
+
+/// Santa's path, recorded once to answer questions about any step of it.
+/// Step 0 is the starting house, step `k` the house after `k` instructions.
+/// Like `deliver`, no house is visited when there are no instructions.
+struct Replay {
+    path: Vec<(i32, i32)>,
+    first_visits: HashMap<(i32, i32), usize>,
+    /// The step each new house was reached at, in order. It's sorted, and its
+    /// length is the number of houses visited.
+    new_house_steps: Vec<usize>,
+}
+
+impl Replay {
+    fn new(input: &str, alphabet: &Alphabet) -> Result<Replay, DeliveryError> {
         let mut pos = (0, 0);
-        for direct in input.chars().skip(actor).step_by(2) {
-            pos = next_pos(direct, pos);
-            visited.insert(pos);
+        let mut path = vec![pos];
+        let (mut first_visits, mut new_house_steps) = match input.is_empty() {
+            true => (HashMap::new(), vec![]),
+            false => (HashMap::from([(pos, 0)]), vec![0]),
+        };
+        for (i, direct) in input.chars().enumerate() {
+            pos = alphabet.next_pos(i, direct, pos)?;
+            path.push(pos);
+            if let Entry::Vacant(e) = first_visits.entry(pos) {
+                e.insert(i + 1);
+                new_house_steps.push(i + 1);
+            }
         }
+        Ok(Replay {
+            path,
+            first_visits,
+            new_house_steps,
+        })
+    }
+
+    /// The number of instructions.
+    fn steps(&self) -> usize {
+        self.path.len() - 1
+    }
+
+    /// Where Santa is after `step` instructions.
+    fn position(&self, step: usize) -> Option<(i32, i32)> {
+        self.path.get(step).cloned()
+    }
+
+    /// The step at which Santa first reached `house`.
+    fn first_visit(&self, house: (i32, i32)) -> Option<usize> {
+        self.first_visits.get(&house).cloned()
+    }
+
+    /// How many houses Santa visited in the first `step` instructions, the
+    /// starting house included.
+    fn visited_after(&self, step: usize) -> usize {
+        self.new_house_steps.partition_point(|s| *s <= step)
     }
 
-    return visited.len();
+    /// The first step after which more than `houses` houses were visited.
+    fn step_exceeding(&self, houses: usize) -> Option<usize> {
+        self.new_house_steps.get(houses).cloned()
+    }
 }
 
-fn next_pos(direction: char, pos: (i32, i32)) -> (i32, i32) {
-    let mut new_x = pos.0;
-    let mut new_y = pos.1;
-    match direction {
-        '^' => new_y += 1,
-        '>' => new_x += 1,
-        'v' => new_y -= 1,
-        '<' => new_x -= 1,
-        _ => (),
-    };
-    return (new_x, new_y);
+#[derive(Debug, PartialEq)]
+enum Query {
+    Position(usize),
+    FirstVisit((i32, i32)),
+    Exceeds(usize),
+}
+
+impl Query {
+    fn parse(option: &str, value: &str) -> Result<Query, String> {
+        let invalid = || format!("invalid value for {}: \"{}\"", option, value);
+        match option {
+            "--position" => Ok(Query::Position(value.parse().map_err(|_| invalid())?)),
+            "--exceeds" => Ok(Query::Exceeds(value.parse().map_err(|_| invalid())?)),
+            "--first-visit" => {
+                let (x, y) = value.split_once(',').ok_or_else(invalid)?;
+                Ok(Query::FirstVisit((
+                    x.trim().parse().map_err(|_| invalid())?,
+                    y.trim().parse().map_err(|_| invalid())?,
+                )))
+            }
+            _ => Err(format!("unknown query: \"{}\"", option)),
+        }
+    }
+
+    fn answer(&self, replay: &Replay) -> String {
+        match self {
+            Query::Position(step) => match replay.position(*step) {
+                Some(p) => format!(
+                    "after step {} Santa is at {:?}, {} houses visited",
+                    step,
+                    p,
+                    replay.visited_after(*step)
+                ),
+                None => format!("there is no step {}, the last is {}", step, replay.steps()),
+            },
+            Query::FirstVisit(house) => match replay.first_visit(*house) {
+                Some(step) => format!("house {:?} was first reached at step {}", house, step),
+                None => format!("house {:?} was never reached", house),
+            },
+            Query::Exceeds(houses) => match replay.step_exceeding(*houses) {
+                Some(step) => format!("more than {} houses visited after step {}", houses, step),
+                None => format!("never more than {} houses visited", houses),
+            },
+        }
+    }
+}
+
+/// Maps instruction characters to `(dx, dy)` moves. North is `+y`.
+#[derive(Debug, Clone, PartialEq)]
+struct Alphabet {
+    moves: HashMap<char, (i32, i32)>,
+    /// Unmapped characters don't move instead of being an error.
+    lenient: bool,
+}
+
+impl Alphabet {
+    fn new(moves: impl IntoIterator<Item = (char, (i32, i32))>) -> Alphabet {
+        Alphabet {
+            moves: moves.into_iter().collect(),
+            lenient: false,
+        }
+    }
+
+    /// The puzzle's `^ > v <`.
+    fn arrows() -> Alphabet {
+        Alphabet::new([('^', (0, 1)), ('>', (1, 0)), ('v', (0, -1)), ('<', (-1, 0))])
+    }
+
+    /// The arrows plus diagonals on the roguelike keys: `y` north west, `u`
+    /// north east, `b` south west and `n` south east.
+    fn eight_way() -> Alphabet {
+        let mut alphabet = Alphabet::arrows();
+        alphabet.moves.extend([
+            ('y', (-1, 1)),
+            ('u', (1, 1)),
+            ('b', (-1, -1)),
+            ('n', (1, -1)),
+        ]);
+        alphabet
+    }
+
+    /// Moves between flat topped hexagons in axial `(q, r)` coordinates, laid
+    /// out like the keys around `s`: `w` north, `e` north east, `d` south
+    /// east, `s` south, `a` south west and `q` north west.
+    fn hex() -> Alphabet {
+        Alphabet::new([
+            ('w', (0, -1)),
+            ('e', (1, -1)),
+            ('d', (1, 0)),
+            ('s', (0, 1)),
+            ('a', (-1, 1)),
+            ('q', (-1, 0)),
+        ])
+    }
+
+    /// Treats unmapped characters as staying at the same house, like the
+    /// puzzle answers always did.
+    fn lenient(mut self) -> Alphabet {
+        self.lenient = true;
+        self
+    }
+
+    /// Where the instruction at `offset` leads from `pos`.
+    fn next_pos(
+        &self,
+        offset: usize,
+        direction: char,
+        pos: (i32, i32),
+    ) -> Result<(i32, i32), DeliveryError> {
+        match self.moves.get(&direction) {
+            Some((dx, dy)) => match (pos.0.checked_add(*dx), pos.1.checked_add(*dy)) {
+                (Some(x), Some(y)) => Ok((x, y)),
+                _ => Err(DeliveryError::OutOfBounds(offset)),
+            },
+            None if self.lenient => Ok(pos),
+            None => Err(DeliveryError::UnmappedCharacter(offset, direction)),
+        }
+    }
+}
+
+/// Parses a built-in alphabet name or a custom one written as space separated
+/// `<char>=<dx>,<dy>` entries, e.g. `U=0,1 D=0,-1`.
+impl FromStr for Alphabet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "arrows" => return Ok(Alphabet::arrows()),
+            "eight-way" => return Ok(Alphabet::eight_way()),
+            "hex" => return Ok(Alphabet::hex()),
+            _ => {}
+        }
+        let mut moves = HashMap::new();
+        for entry in s.split_whitespace() {
+            let invalid = || format!("invalid alphabet entry: \"{}\"", entry);
+            let mut chars = entry.chars();
+            let c = chars.next().ok_or_else(invalid)?;
+            let (dx, dy) = chars
+                .as_str()
+                .strip_prefix('=')
+                .and_then(|d| d.split_once(','))
+                .ok_or_else(invalid)?;
+            let delta = (
+                dx.parse::<i32>().map_err(|_| invalid())?,
+                dy.parse::<i32>().map_err(|_| invalid())?,
+            );
+            if moves.insert(c, delta).is_some() {
+                return Err(format!("character {:?} is mapped twice", c));
+            }
+        }
+        if moves.is_empty() {
+            return Err(format!("unknown alphabet: \"{}\"", s));
+        }
+        Ok(Alphabet::new(moves))
+    }
+}
+
+pub fn check_input(input: &str) -> Vec<InputProblem> {
+    check_single_line(input, |c| matches!(c, '^' | '>' | 'v' | '<'))
 }
 
 #[cfg(test)]
@@ -85,4 +784,256 @@ mod tests {
         assert_eq!(spread_presents_with_robot("^v^v^v^v^v"), 11);
         assert_eq!(spread_presents_with_robot("<><><><>"), 9);
     }
+    #[test]
+    fn test_spread_presents_with_actors() {
+        let delivery =
+            spread_presents_with_actors("^>v<", 2, &Dispatch::RoundRobin, &Alphabet::arrows())
+                .unwrap();
+        assert_eq!(delivery.union, 3);
+        assert_eq!(delivery.per_actor[0], HashSet::from([(0, 0), (0, 1)]));
+        assert_eq!(delivery.per_actor[1], HashSet::from([(0, 0), (1, 0)]));
+
+        // Blocks of two: santa gets `^^`, the robot `>>`, then santa `vv`.
+        let delivery =
+            spread_presents_with_actors("^^>>vv", 2, &Dispatch::Chunked(2), &Alphabet::arrows())
+                .unwrap();
+        assert_eq!(delivery.union, 5);
+        assert_eq!(delivery.per_actor[0].len(), 3);
+
+        // Santa takes three instructions for each one of the robot.
+        let weighted = Dispatch::Weighted(vec![3, 1]);
+        let delivery =
+            spread_presents_with_actors("^^^>^^^>", 2, &weighted, &Alphabet::arrows()).unwrap();
+        assert_eq!(delivery.per_actor[0].len(), 7);
+        assert_eq!(delivery.per_actor[1].len(), 3);
+        assert_eq!(delivery.union, 9);
+
+        let delivery =
+            spread_presents_with_actors("", 3, &Dispatch::RoundRobin, &Alphabet::arrows()).unwrap();
+        assert_eq!(delivery.union, 0);
+        assert_eq!(delivery.per_actor.len(), 3);
+    }
+    #[test]
+    fn test_count_visits() {
+        let visits = count_visits("^>v<", 2, &Dispatch::RoundRobin, &Alphabet::arrows()).unwrap();
+        assert_eq!(
+            visits[&(0, 0)],
+            HouseVisits {
+                presents: 4,
+                first_step: 0,
+                first_actor: 0
+            }
+        );
+        assert_eq!(
+            visits[&(1, 0)],
+            HouseVisits {
+                presents: 1,
+                first_step: 2,
+                first_actor: 1
+            }
+        );
+        assert_eq!(visits.values().map(|v| v.presents).sum::<usize>(), 2 + 4);
+        assert_eq!(
+            presents_histogram(&visits),
+            BTreeMap::from([(1, 2), (4, 1)])
+        );
+
+        let visits = count_visits("^^vv>", 1, &Dispatch::RoundRobin, &Alphabet::arrows()).unwrap();
+        assert_eq!(visits.len(), spread_presents("^^vv>"));
+        assert_eq!(visits[&(0, 1)].presents, 2);
+        assert_eq!(visits[&(0, 1)].first_step, 1);
+        assert!(
+            count_visits("", 1, &Dispatch::RoundRobin, &Alphabet::arrows())
+                .unwrap()
+                .is_empty()
+        );
+    }
+    #[test]
+    fn test_render() {
+        let visits =
+            count_visits("^>>vv<<^", 1, &Dispatch::RoundRobin, &Alphabet::arrows()).unwrap();
+        assert_eq!(render_ascii(&visits).unwrap(), "111\n2 1\n111\n");
+        assert_eq!(render_ascii(&HashMap::new()).unwrap(), "");
+
+        let ppm = render_ppm(&visits).unwrap();
+        assert!(ppm.starts_with(b"P6\n3 3\n255\n"));
+        assert_eq!(ppm.len(), 11 + 3 * 3 * 3);
+        // The empty house in the middle stays black, the busiest is yellow.
+        assert_eq!(&ppm[11 + 4 * 3..11 + 5 * 3], &[0, 0, 0]);
+        assert_eq!(&ppm[11 + 3 * 3..11 + 4 * 3], &[255, 255, 0]);
+
+        // Houses at opposite ends of the coordinates, and far apart on a
+        // diagonal.
+        let far: Alphabet = "R=2147483647,0 L=-2147483648,0 D=100000,100000"
+            .parse()
+            .unwrap();
+        for (input, width, height) in [
+            ("RL", 2147483649, 1),
+            (&"D".repeat(46)[..], 4600001, 4600001),
+        ] {
+            let visits = count_visits(input, 1, &Dispatch::RoundRobin, &far).unwrap();
+            let too_large = MapTooLarge { width, height };
+            assert_eq!(render_ascii(&visits).unwrap_err(), too_large);
+            assert_eq!(render_ppm(&visits).unwrap_err(), too_large);
+        }
+    }
+    #[test]
+    fn test_replay() {
+        let replay = Replay::new("^>v<^", &Alphabet::arrows()).unwrap();
+        assert_eq!(replay.steps(), 5);
+        assert_eq!(replay.position(0), Some((0, 0)));
+        assert_eq!(replay.position(2), Some((1, 1)));
+        assert_eq!(replay.position(5), Some((0, 1)));
+        assert_eq!(replay.position(6), None);
+
+        assert_eq!(replay.first_visit((0, 0)), Some(0));
+        assert_eq!(replay.first_visit((0, 1)), Some(1));
+        assert_eq!(replay.first_visit((1, 0)), Some(3));
+        assert_eq!(replay.first_visit((5, 5)), None);
+
+        assert_eq!(replay.visited_after(0), 1);
+        assert_eq!(replay.visited_after(2), 3);
+        assert_eq!(replay.visited_after(100), 4);
+        assert_eq!(replay.step_exceeding(0), Some(0));
+        assert_eq!(replay.step_exceeding(1), Some(1));
+        assert_eq!(replay.step_exceeding(3), Some(3));
+        assert_eq!(replay.step_exceeding(4), None);
+
+        let empty = Replay::new("", &Alphabet::arrows()).unwrap();
+        assert_eq!(empty.steps(), 0);
+        assert_eq!(empty.position(0), Some((0, 0)));
+        assert_eq!(empty.first_visit((0, 0)), None);
+        assert_eq!(empty.visited_after(0), spread_presents(""));
+        assert_eq!(empty.step_exceeding(0), None);
+
+        let input = "^^<<v>>>vv<";
+        assert_eq!(
+            Replay::new(input, &Alphabet::arrows())
+                .unwrap()
+                .visited_after(input.len()),
+            spread_presents(input)
+        );
+    }
+    #[test]
+    fn test_query() {
+        let replay = Replay::new("^>", &Alphabet::arrows()).unwrap();
+        let answer = |option, value| Query::parse(option, value).unwrap().answer(&replay);
+        assert_eq!(
+            answer("--position", "1"),
+            "after step 1 Santa is at (0, 1), 2 houses visited"
+        );
+        assert_eq!(
+            answer("--position", "3"),
+            "there is no step 3, the last is 2"
+        );
+        assert_eq!(
+            answer("--first-visit", "1, 1"),
+            "house (1, 1) was first reached at step 2"
+        );
+        assert_eq!(
+            answer("--exceeds", "2"),
+            "more than 2 houses visited after step 2"
+        );
+        assert_eq!(answer("--exceeds", "3"), "never more than 3 houses visited");
+        assert!(Query::parse("--first-visit", "1").is_err());
+        assert!(Query::parse("--position", "-1").is_err());
+    }
+    #[test]
+    fn test_alphabets() {
+        let delivery = |input, alphabet: &Alphabet| {
+            spread_presents_with_actors(input, 1, &Dispatch::RoundRobin, alphabet)
+        };
+        assert_eq!(
+            delivery("^>x", &Alphabet::arrows()).unwrap_err(),
+            DeliveryError::UnmappedCharacter(2, 'x')
+        );
+        assert_eq!(spread_presents("^>x"), 3);
+
+        // Around a square with the diagonals cuts every corner.
+        let replay = Replay::new("unby", &Alphabet::eight_way()).unwrap();
+        assert_eq!(replay.position(2), Some((2, 0)));
+        assert_eq!(replay.position(4), Some((0, 0)));
+        assert_eq!(delivery("unby", &Alphabet::eight_way()).unwrap().union, 4);
+
+        // Six hex steps around a ring come back to the start, three forward
+        // and three back visit four houses.
+        let hex = Alphabet::hex();
+        assert_eq!(
+            Replay::new("wedsaq", &hex).unwrap().position(6),
+            Some((0, 0))
+        );
+        assert_eq!(Replay::new("wedsaq", &hex).unwrap().visited_after(6), 6);
+        assert_eq!(delivery("eeeaaa", &hex).unwrap().union, 4);
+        assert_eq!("hex".parse(), Ok(hex));
+
+        let custom: Alphabet = "U=0,1 D=0,-1 ==2,0".parse().unwrap();
+        assert_eq!(
+            Replay::new("UU=D", &custom).unwrap().position(4),
+            Some((2, 1))
+        );
+        let far: Alphabet = "R=2147483647,0 L=-2147483648,0".parse().unwrap();
+        assert_eq!(
+            delivery("RR", &far).unwrap_err(),
+            DeliveryError::OutOfBounds(1)
+        );
+        assert_eq!(
+            delivery("RLL", &far).unwrap_err(),
+            DeliveryError::OutOfBounds(2)
+        );
+        assert_eq!(delivery("RL", &far).unwrap().union, 3);
+        assert!(Replay::new("LL", &far).is_err());
+        assert!("U=0 D=0,-1".parse::<Alphabet>().is_err());
+        assert!("U=0,1 U=1,0".parse::<Alphabet>().is_err());
+        assert!("diagonal".parse::<Alphabet>().is_err());
+    }
+    #[test]
+    fn test_dispatch() {
+        assert_eq!("round-robin".parse(), Ok(Dispatch::RoundRobin));
+        assert_eq!("chunked:4".parse(), Ok(Dispatch::Chunked(4)));
+        assert_eq!(
+            "weighted:2,0,1".parse(),
+            Ok(Dispatch::Weighted(vec![2, 0, 1]))
+        );
+        assert!("weighted:2,x".parse::<Dispatch>().is_err());
+        assert!("chunked".parse::<Dispatch>().is_err());
+
+        let err = |actors, dispatch: Dispatch| {
+            spread_presents_with_actors("^", actors, &dispatch, &Alphabet::arrows()).unwrap_err()
+        };
+        assert_eq!(err(0, Dispatch::RoundRobin), DeliveryError::NoActors);
+        assert_eq!(err(2, Dispatch::Chunked(0)), DeliveryError::EmptyRound);
+        assert_eq!(
+            err(3, Dispatch::Weighted(vec![1, 2])),
+            DeliveryError::WeightCount(3, 2)
+        );
+        assert_eq!(
+            err(2, Dispatch::Weighted(vec![0, 0])),
+            DeliveryError::EmptyRound
+        );
+
+        let actors = |dispatch: Dispatch, actors, steps| {
+            let turns = dispatch.turns(actors).unwrap();
+            (0..steps).map(|s| turns.actor(s)).collect::<Vec<_>>()
+        };
+        assert_eq!(actors(Dispatch::RoundRobin, 3, 7), [0, 1, 2, 0, 1, 2, 0]);
+        assert_eq!(actors(Dispatch::Chunked(2), 2, 6), [0, 0, 1, 1, 0, 0]);
+        assert_eq!(
+            actors(Dispatch::Weighted(vec![2, 0, 1]), 3, 7),
+            [0, 0, 2, 0, 0, 2, 0]
+        );
+        // Huge rounds are never listed.
+        assert_eq!(actors(Dispatch::Chunked(1000000000), 2, 3), [0, 0, 0]);
+        assert_eq!(
+            actors(Dispatch::Weighted(vec![usize::MAX, usize::MAX]), 2, 2),
+            [0, 0]
+        );
+        let delivery = spread_presents_with_actors(
+            "^>v",
+            4,
+            &Dispatch::Chunked(1000000000),
+            &Alphabet::arrows(),
+        )
+        .unwrap();
+        assert_eq!(delivery.union, 4);
+    }
 }
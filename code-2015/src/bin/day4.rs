@@ -1,14 +1,88 @@
-use std::io;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, error, fmt, fs,
+    io::{self, Write},
+    num::NonZeroUsize,
+    ops::Range,
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Condvar, Mutex,
+    },
+    thread,
+};
+
+use code_2015::{
+    check::{check_single_line, InputProblem},
+    trace,
+};
+use serde_json::{json, Value};
+use sha2::Digest;
 
 fn main() -> io::Result<()> {
+    trace::init();
+    let options = match Options::from_args(env::args().skip(1)) {
+        Ok(o) => o,
+        Err(e) => {
+            println!("{}", e);
+            println!(
+                "usage: day4 [--threads <n> | --single-threaded] [--predicate <predicate>]... \
+                 [--checkpoint <file> [--resume]] [--hash <md5|sha1|sha256>] \
+                 [--all <start>..<end>]"
+            );
+            println!("       predicate: zeros:<n>, prefix:<hex>, suffix:<hex> or bits:<n>");
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, e));
+        }
+    };
+    let mut checkpoints = match options.checkpoint.as_ref() {
+        Some(path) => Some(Checkpoints::load(path.clone(), options.resume)?),
+        None => None,
+    };
+    let threads = options.threads.unwrap_or(NonZeroUsize::MIN);
+    let hasher = options.hasher;
+    let mut mine = |input: &str, predicate: &Predicate| match checkpoints.as_mut() {
+        Some(c) => c.mine(input, hasher, predicate, threads),
+        None => Ok(mine_from(input, hasher, predicate, threads, 0, &|_| {})),
+    };
     let mut buffer = String::new();
-    let read_ret = io::stdin().read_line(&mut buffer);
+    let read_ret = trace::parse(4, || io::stdin().read_line(&mut buffer));
     match read_ret {
         Ok(_) => {
-            let ret = check_md5(buffer.as_str(), 5);
-            println!("coin with 5 leading zero is: {}", ret);
-            let ret = check_md5(buffer.as_str(), 6);
-            println!("coin with 6 leading zero is: {}", ret);
+            if let Some(range) = options.range {
+                let mut predicates = options.predicates;
+                if predicates.is_empty() {
+                    predicates.push(Predicate::leading_zeros(5));
+                }
+                for predicate in predicates.iter() {
+                    println!(
+                        "coins matching {} in {}..{}:",
+                        predicate, range.start, range.end
+                    );
+                    trace::solve_all(4, || {
+                        scan(
+                            &buffer,
+                            hasher,
+                            predicate,
+                            threads,
+                            range.clone(),
+                            |nonce, digest| println!("{} {}", nonce, hex(digest)),
+                        )
+                    });
+                }
+                return Ok(());
+            }
+            for (part, zeros) in [(1, 5), (2, 6)] {
+                let ret = trace::solve(4, part, || match options.threads {
+                    Some(_) => mine(buffer.as_str(), &Predicate::leading_zeros(zeros)),
+                    None => Ok(check_md5(buffer.as_str(), zeros)),
+                })?;
+                println!("coin with {} leading zero is: {}", zeros, ret);
+            }
+            for predicate in options.predicates.iter() {
+                let ret = trace::solve_all(4, || mine(buffer.as_str(), predicate))?;
+                println!("coin matching {} is: {}", predicate, ret);
+            }
             return Ok(());
         }
         Err(err) => {
@@ -18,6 +92,98 @@ fn main() -> io::Result<()> {
     }
 }
 
+struct Options {
+    /// `None` mines on the calling thread with `check_md5`.
+    threads: Option<NonZeroUsize>,
+    predicates: Vec<Predicate>,
+    checkpoint: Option<PathBuf>,
+    resume: bool,
+    hasher: &'static dyn Hasher,
+    /// Lists every matching nonce in the range instead of the first one.
+    range: Option<Range<u64>>,
+}
+
+impl Options {
+    fn from_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options {
+            threads: Some(default_threads()),
+            predicates: vec![],
+            checkpoint: None,
+            resume: false,
+            hasher: &Md5,
+            range: None,
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--threads" => {
+                    let n = args.next().ok_or("missing count after --threads")?;
+                    options.threads = Some(
+                        n.parse()
+                            .map_err(|_| format!("invalid thread count: \"{}\"", n))?,
+                    );
+                }
+                "--single-threaded" => options.threads = None,
+                "--predicate" => {
+                    let predicate = args.next().ok_or("missing predicate after --predicate")?;
+                    options.predicates.push(predicate.parse()?);
+                }
+                "--checkpoint" => {
+                    let path = args.next().ok_or("missing file after --checkpoint")?;
+                    options.checkpoint = Some(path.into());
+                }
+                "--resume" => options.resume = true,
+                "--hash" => {
+                    let name = args.next().ok_or("missing hash after --hash")?;
+                    options.hasher = HASHERS
+                        .into_iter()
+                        .find(|h| h.name() == name)
+                        .ok_or_else(|| format!("unknown hash: \"{}\"", name))?;
+                }
+                "--all" => {
+                    let range = args.next().ok_or("missing range after --all")?;
+                    let invalid = || format!("invalid range: \"{}\"", range);
+                    let (start, end) = range.split_once("..").ok_or_else(invalid)?;
+                    let start = start.parse().map_err(|_| invalid())?;
+                    let end = end.parse().map_err(|_| invalid())?;
+                    if start > end {
+                        return Err(invalid());
+                    }
+                    options.range = Some(start..end);
+                }
+                _ => return Err(format!("unknown option: \"{}\"", arg)),
+            }
+        }
+        if options.resume && options.checkpoint.is_none() {
+            return Err("--resume needs --checkpoint".into());
+        }
+        for predicate in options.predicates.iter() {
+            predicate.check_digest_len(options.hasher.digest_len())?;
+        }
+        if options.range.is_some() && options.checkpoint.is_some() {
+            return Err("--all can't be combined with --checkpoint".into());
+        }
+        if options.threads.is_none()
+            && options.range.is_none()
+            && (options.checkpoint.is_some() || options.hasher.name() != "md5")
+        {
+            return Err("--single-threaded only supports md5 without --checkpoint".into());
+        }
+        Ok(options)
+    }
+}
+
+pub fn part_one(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(mine_md5(input, &Predicate::leading_zeros(5), default_threads()).to_string())
+}
+
+pub fn part_two(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(mine_md5(input, &Predicate::leading_zeros(6), default_threads()).to_string())
+}
+
+fn default_threads() -> NonZeroUsize {
+    thread::available_parallelism().unwrap_or(NonZeroUsize::MIN)
+}
+
 fn check_md5(input: &str, num_of_zeros: usize) -> u64 {
     let mut i = 0;
     loop {
@@ -25,12 +191,548 @@ fn check_md5(input: &str, num_of_zeros: usize) -> u64 {
         let k = format!("{}{}", input.trim(), i);
         let digest = format!("{:x}", md5::compute(k.as_bytes()));
         if digest.starts_with(leading_zeros.as_str()) {
+            tracing::debug!(nonce = i, "coin found");
             return i;
         }
         i += 1;
+        if i.is_multiple_of(100_000) {
+            tracing::debug!(nonces = i, "md5 nonces tried");
+        }
     }
 }
 
+/// Nonces handed to a thread at a time.
+const CHUNK: u64 = 10_000;
+
+/// Chunks scanned between two progress reports.
+const PROGRESS_CHUNKS: u64 = 100;
+
+/// Same as `check_md5` but spread over `threads` threads.
+fn mine_md5(input: &str, predicate: &Predicate, threads: NonZeroUsize) -> u64 {
+    mine_from(input, &Md5, predicate, threads, 0, &|_| {})
+}
+
+/// Mines from nonce `first` on, which gives the same result as starting from
+/// 0 when no nonce below `first` matches.
+///
+/// Threads claim chunks of nonces in increasing order and scan each one from
+/// its start, so every chunk below the best nonce found so far is scanned in
+/// full and the smallest qualifying nonce wins whatever the timing.
+///
+/// `on_progress(n)` is called from time to time, and once more with the
+/// answer at the end, with a nonce `n` below which every nonce was scanned.
+fn mine_from(
+    input: &str,
+    hasher: &dyn Hasher,
+    predicate: &Predicate,
+    threads: NonZeroUsize,
+    first: u64,
+    on_progress: &(dyn Fn(u64) + Sync),
+) -> u64 {
+    let key = input.trim().as_bytes();
+    let next_chunk = AtomicU64::new(0);
+    let best = AtomicU64::new(u64::MAX);
+    let progress = Mutex::new(Progress {
+        scanned: 0,
+        reported: 0,
+        done: BTreeSet::new(),
+    });
+    thread::scope(|s| {
+        for _ in 0..threads.get() {
+            s.spawn(|| {
+                let mut buffer = key.to_vec();
+                let mut digest = vec![];
+                'chunks: loop {
+                    let chunk = next_chunk.fetch_add(1, Ordering::Relaxed);
+                    let start = first + chunk * CHUNK;
+                    if start >= best.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    if chunk.is_multiple_of(10) {
+                        tracing::debug!(nonces = start, "{} nonces tried", hasher.name());
+                    }
+                    for nonce in start..start + CHUNK {
+                        buffer.truncate(key.len());
+                        let _ = write!(buffer, "{}", nonce);
+                        hasher.hash(&buffer, &mut digest);
+                        if predicate.matches(&digest) {
+                            best.fetch_min(nonce, Ordering::Relaxed);
+                            continue 'chunks;
+                        }
+                    }
+                    let mut progress = progress.lock().unwrap();
+                    if let Some(scanned) = progress.complete(chunk) {
+                        on_progress(first + scanned * CHUNK);
+                    }
+                }
+            });
+        }
+    });
+    let nonce = best.into_inner();
+    tracing::debug!(nonce, "coin found");
+    on_progress(nonce);
+    nonce
+}
+
+/// Calls `on_match` with every nonce in `range` whose digest matches, in
+/// nonce order. Chunks are hashed on `threads` threads and handed back to
+/// the calling thread, which holds early chunks until the ones before them
+/// are done. Threads don't start a chunk more than `2 * threads` ahead of
+/// the next one to hand over, so a slow chunk can't pile up the others.
+fn scan(
+    input: &str,
+    hasher: &dyn Hasher,
+    predicate: &Predicate,
+    threads: NonZeroUsize,
+    range: Range<u64>,
+    mut on_match: impl FnMut(u64, &[u8]),
+) {
+    let key = input.trim().as_bytes();
+    let chunks = (range.end - range.start).div_ceil(CHUNK);
+    let window = threads.get() as u64 * 2;
+    let next_chunk = AtomicU64::new(0);
+    let handed_over = (Mutex::new(0), Condvar::new());
+    let (sender, receiver) = mpsc::sync_channel(window as usize);
+    thread::scope(|s| {
+        for _ in 0..threads.get() {
+            let sender = sender.clone();
+            let next_chunk = &next_chunk;
+            let handed_over = &handed_over;
+            let range = &range;
+            s.spawn(move || {
+                let mut buffer = key.to_vec();
+                let mut digest = vec![];
+                loop {
+                    let chunk = next_chunk.fetch_add(1, Ordering::Relaxed);
+                    if chunk >= chunks {
+                        return;
+                    }
+                    let (next, ready) = handed_over;
+                    drop(ready.wait_while(next.lock().unwrap(), |next| chunk >= *next + window));
+                    let start = range.start + chunk * CHUNK;
+                    let mut matches = vec![];
+                    for nonce in start..start.saturating_add(CHUNK).min(range.end) {
+                        buffer.truncate(key.len());
+                        let _ = write!(buffer, "{}", nonce);
+                        hasher.hash(&buffer, &mut digest);
+                        if predicate.matches(&digest) {
+                            matches.push((nonce, digest.clone()));
+                        }
+                    }
+                    if sender.send((chunk, matches)).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+        drop(sender);
+        let mut pending = BTreeMap::new();
+        let mut next = 0;
+        for (chunk, matches) in receiver {
+            pending.insert(chunk, matches);
+            while let Some(matches) = pending.remove(&next) {
+                for (nonce, digest) in matches {
+                    on_match(nonce, &digest);
+                }
+                next += 1;
+            }
+            let (handed_over, ready) = &handed_over;
+            *handed_over.lock().unwrap() = next;
+            ready.notify_all();
+        }
+    });
+    tracing::debug!(range = ?range, hash = hasher.name(), "range scanned");
+}
+
+fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// A hash function to mine with.
+trait Hasher: Sync {
+    /// The name on the command line and in checkpoint files.
+    fn name(&self) -> &'static str;
+
+    /// The number of bytes in a digest.
+    fn digest_len(&self) -> usize;
+
+    /// Replaces the content of `digest` with the hash of `input`.
+    fn hash(&self, input: &[u8], digest: &mut Vec<u8>);
+}
+
+struct Md5;
+
+impl Hasher for Md5 {
+    fn name(&self) -> &'static str {
+        "md5"
+    }
+
+    fn digest_len(&self) -> usize {
+        16
+    }
+
+    fn hash(&self, input: &[u8], digest: &mut Vec<u8>) {
+        digest.clear();
+        digest.extend_from_slice(&md5::compute(input).0);
+    }
+}
+
+struct Sha1;
+
+impl Hasher for Sha1 {
+    fn name(&self) -> &'static str {
+        "sha1"
+    }
+
+    fn digest_len(&self) -> usize {
+        20
+    }
+
+    fn hash(&self, input: &[u8], digest: &mut Vec<u8>) {
+        digest.clear();
+        digest.extend_from_slice(&sha1::Sha1::digest(input));
+    }
+}
+
+struct Sha256;
+
+impl Hasher for Sha256 {
+    fn name(&self) -> &'static str {
+        "sha256"
+    }
+
+    fn digest_len(&self) -> usize {
+        32
+    }
+
+    fn hash(&self, input: &[u8], digest: &mut Vec<u8>) {
+        digest.clear();
+        digest.extend_from_slice(&sha2::Sha256::digest(input));
+    }
+}
+
+const HASHERS: [&dyn Hasher; 3] = [&Md5, &Sha1, &Sha256];
+
+/// Chunks fully scanned without a match.
+struct Progress {
+    /// Every chunk below this one is done.
+    scanned: u64,
+    /// The value of `scanned` last reported.
+    reported: u64,
+    /// Done chunks above `scanned`.
+    done: BTreeSet<u64>,
+}
+
+impl Progress {
+    /// Marks `chunk` done and returns the new `scanned` when it's time to
+    /// report it.
+    fn complete(&mut self, chunk: u64) -> Option<u64> {
+        self.done.insert(chunk);
+        while self.done.remove(&self.scanned) {
+            self.scanned += 1;
+        }
+        if self.scanned < self.reported + PROGRESS_CHUNKS {
+            return None;
+        }
+        self.reported = self.scanned;
+        Some(self.scanned)
+    }
+}
+
+/// How far earlier searches got, saved to a JSON file as an array of
+/// `{"key", "hash", "predicate", "scanned"}` objects where no nonce below
+/// `scanned` matches.
+struct Checkpoints {
+    path: PathBuf,
+    entries: Vec<Checkpoint>,
+}
+
+struct Checkpoint {
+    key: String,
+    hash: String,
+    predicate: String,
+    scanned: u64,
+}
+
+impl Checkpoint {
+    fn is_for(&self, key: &str, hasher: &dyn Hasher, predicate: &str) -> bool {
+        self.key == key && self.hash == hasher.name() && self.predicate == predicate
+    }
+}
+
+impl Checkpoints {
+    /// Reads the saved searches when resuming, a missing file is a fresh
+    /// start. Otherwise every search starts from 0 and overwrites the file.
+    fn load(path: PathBuf, resume: bool) -> io::Result<Checkpoints> {
+        let mut checkpoints = Checkpoints {
+            path,
+            entries: vec![],
+        };
+        if !resume {
+            return Ok(checkpoints);
+        }
+        let content = match fs::read_to_string(&checkpoints.path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(checkpoints),
+            Err(e) => return Err(e),
+        };
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid checkpoint file: {}", checkpoints.path.display()),
+            )
+        };
+        let value: Value = serde_json::from_str(&content).map_err(|_| invalid())?;
+        for entry in value.as_array().ok_or_else(invalid)? {
+            let field = |name: &str| entry.get(name).and_then(|v| v.as_str());
+            let scanned = entry.get("scanned").and_then(|s| s.as_u64());
+            // Files from before hashes were selectable only hold md5 searches.
+            let hash = field("hash").unwrap_or("md5");
+            match (field("key"), field("predicate"), scanned) {
+                (Some(key), Some(predicate), Some(scanned)) => {
+                    checkpoints.entries.push(Checkpoint {
+                        key: key.to_string(),
+                        hash: hash.to_string(),
+                        predicate: predicate.to_string(),
+                        scanned,
+                    })
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(checkpoints)
+    }
+
+    fn scanned(&self, key: &str, hasher: &dyn Hasher, predicate: &Predicate) -> u64 {
+        let predicate = predicate.to_string();
+        self.entries
+            .iter()
+            .find(|c| c.is_for(key, hasher, &predicate))
+            .map_or(0, |c| c.scanned)
+    }
+
+    fn record(
+        &mut self,
+        key: &str,
+        hasher: &dyn Hasher,
+        predicate: &Predicate,
+        scanned: u64,
+    ) -> io::Result<()> {
+        let predicate = predicate.to_string();
+        match self
+            .entries
+            .iter_mut()
+            .find(|c| c.is_for(key, hasher, &predicate))
+        {
+            Some(c) => c.scanned = scanned,
+            None => self.entries.push(Checkpoint {
+                key: key.to_string(),
+                hash: hasher.name().to_string(),
+                predicate,
+                scanned,
+            }),
+        }
+        let value = self
+            .entries
+            .iter()
+            .map(|c| {
+                json!({
+                    "key": c.key,
+                    "hash": c.hash,
+                    "predicate": c.predicate,
+                    "scanned": c.scanned
+                })
+            })
+            .collect::<Vec<Value>>();
+        // Written aside then renamed, so an interrupted write can't lose the
+        // previous checkpoint.
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(&value)?)?;
+        fs::rename(&tmp, &self.path)
+    }
+
+    /// Mines from where the last search for the same key, hash and predicate
+    /// got to, saving the progress along the way.
+    fn mine(
+        &mut self,
+        input: &str,
+        hasher: &dyn Hasher,
+        predicate: &Predicate,
+        threads: NonZeroUsize,
+    ) -> io::Result<u64> {
+        let key = input.trim();
+        let first = self.scanned(key, hasher, predicate);
+        let checkpoints = Mutex::new((self, None));
+        let nonce = mine_from(input, hasher, predicate, threads, first, &|scanned| {
+            let (checkpoints, error) = &mut *checkpoints.lock().unwrap();
+            if let Err(e) = checkpoints.record(key, hasher, predicate, scanned) {
+                error.get_or_insert(e);
+            }
+        });
+        match checkpoints.into_inner().unwrap().1 {
+            Some(e) => Err(e),
+            None => Ok(nonce),
+        }
+    }
+}
+
+/// A condition on a raw digest, checked without formatting it as hex.
+#[derive(Debug, Clone, PartialEq)]
+enum Predicate {
+    /// The hex form starts with the pattern.
+    Prefix(HexPattern),
+    /// The hex form ends with the pattern.
+    Suffix(HexPattern),
+    /// The digest starts with at least this many zero bits.
+    LeadingZeroBits(u32),
+}
+
+impl Predicate {
+    /// The hex form starts with `n` zeros, the puzzle's rule.
+    fn leading_zeros(n: usize) -> Predicate {
+        Predicate::Prefix(HexPattern::parse(&"0".repeat(n), false).unwrap())
+    }
+
+    /// Fails for a predicate that no digest of `digest_len` bytes matches,
+    /// which would have the miner search forever.
+    fn check_digest_len(&self, digest_len: usize) -> Result<(), String> {
+        let fits = match self {
+            Predicate::Prefix(pattern) | Predicate::Suffix(pattern) => {
+                pattern.digits.len() <= digest_len * 2
+            }
+            Predicate::LeadingZeroBits(bits) => *bits as usize <= digest_len * 8,
+        };
+        match fits {
+            true => Ok(()),
+            false => Err(format!(
+                "predicate {} can't match a {} bit digest",
+                self,
+                digest_len * 8
+            )),
+        }
+    }
+
+    fn matches(&self, digest: &[u8]) -> bool {
+        match self {
+            Predicate::Prefix(pattern) => pattern.matches(digest, false),
+            Predicate::Suffix(pattern) => pattern.matches(digest, true),
+            Predicate::LeadingZeroBits(bits) => {
+                let mut zeros = 0;
+                for b in digest {
+                    zeros += b.leading_zeros();
+                    if *b != 0 || zeros >= *bits {
+                        break;
+                    }
+                }
+                zeros >= *bits
+            }
+        }
+    }
+}
+
+/// Parses `zeros:<n>`, `prefix:<hex>`, `suffix:<hex>` or `bits:<n>`. Hex
+/// patterns may use `.` for any digit.
+impl FromStr for Predicate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid predicate: \"{}\"", s);
+        match s.split_once(':').ok_or_else(invalid)? {
+            ("zeros", n) => Ok(Predicate::leading_zeros(n.parse().map_err(|_| invalid())?)),
+            ("prefix", hex) => Ok(Predicate::Prefix(HexPattern::parse(hex, false)?)),
+            ("suffix", hex) => Ok(Predicate::Suffix(HexPattern::parse(hex, true)?)),
+            ("bits", n) => Ok(Predicate::LeadingZeroBits(
+                n.parse().map_err(|_| invalid())?,
+            )),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Predicate::Prefix(pattern) => write!(f, "prefix:{}", pattern),
+            Predicate::Suffix(pattern) => write!(f, "suffix:{}", pattern),
+            Predicate::LeadingZeroBits(bits) => write!(f, "bits:{}", bits),
+        }
+    }
+}
+
+/// Hex digits turned into byte masks, so matching is a few byte comparisons.
+/// An odd number of digits only covers half of the outermost byte.
+#[derive(Debug, Clone, PartialEq)]
+struct HexPattern {
+    mask: Vec<u8>,
+    value: Vec<u8>,
+    digits: String,
+}
+
+impl HexPattern {
+    /// `at_end` aligns the digits to the end of the digest instead of its
+    /// start.
+    fn parse(digits: &str, at_end: bool) -> Result<HexPattern, String> {
+        let mut nibbles = digits
+            .chars()
+            .map(|c| match c {
+                '.' => Ok(None),
+                _ => c
+                    .to_digit(16)
+                    .map(|d| Some(d as u8))
+                    .ok_or_else(|| format!("invalid hex pattern: \"{}\"", digits)),
+            })
+            .collect::<Result<Vec<Option<u8>>, String>>()?;
+        if nibbles.len() % 2 == 1 {
+            if at_end {
+                nibbles.insert(0, None);
+            } else {
+                nibbles.push(None);
+            }
+        }
+        let (mut mask, mut value) = (vec![], vec![]);
+        for pair in nibbles.chunks(2) {
+            let nibble = |n: Option<u8>| n.map_or((0, 0), |d| (0xf, d));
+            let (high, low) = (nibble(pair[0]), nibble(pair[1]));
+            mask.push(high.0 << 4 | low.0);
+            value.push(high.1 << 4 | low.1);
+        }
+        Ok(HexPattern {
+            mask,
+            value,
+            digits: digits.to_lowercase(),
+        })
+    }
+
+    fn matches(&self, digest: &[u8], at_end: bool) -> bool {
+        if digest.len() < self.mask.len() {
+            return false;
+        }
+        let start = if at_end {
+            digest.len() - self.mask.len()
+        } else {
+            0
+        };
+        digest[start..]
+            .iter()
+            .zip(self.mask.iter().zip(self.value.iter()))
+            .all(|(b, (mask, value))| b & mask == *value)
+    }
+}
+
+impl fmt::Display for HexPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.digits)
+    }
+}
+
+pub fn check_input(input: &str) -> Vec<InputProblem> {
+    if input.trim().is_empty() {
+        return vec![InputProblem::in_input("secret key is empty")];
+    }
+    check_single_line(input, |c| !c.is_whitespace())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -39,4 +741,235 @@ mod tests {
         assert_eq!(check_md5("abcdef", 5), 609043);
         assert_eq!(check_md5("pqrstuv", 5), 1048970);
     }
+    #[test]
+    fn test_mine_md5() {
+        for threads in [1, 3, 8] {
+            let threads = NonZeroUsize::new(threads).unwrap();
+            assert_eq!(
+                mine_md5("abcdef", &Predicate::leading_zeros(5), threads),
+                609043
+            );
+            // Hits in the very first chunk.
+            assert_eq!(
+                mine_md5("abcdef", &Predicate::leading_zeros(1), threads),
+                check_md5("abcdef", 1)
+            );
+        }
+        let threads = NonZeroUsize::new(4).unwrap();
+        assert_eq!(
+            mine_md5("pqrstuv\n", &Predicate::leading_zeros(5), threads),
+            1048970
+        );
+    }
+    #[test]
+    fn test_predicate() {
+        let matches =
+            |spec: &str, digest: &[u8]| spec.parse::<Predicate>().unwrap().matches(digest);
+        assert!(matches("zeros:5", &[0x00, 0x00, 0x0f, 0xff]));
+        assert!(!matches("zeros:5", &[0x00, 0x00, 0x1f, 0xff]));
+        assert!(matches("zeros:4", &[0x00, 0x00, 0x1f, 0xff]));
+        assert!(matches("zeros:0", &[0xff]));
+        assert!(!matches("zeros:3", &[0x00]));
+
+        assert!(matches("prefix:a.C", &[0xa7, 0xcf]));
+        assert!(!matches("prefix:a.c", &[0xa7, 0xbf]));
+        assert!(matches("suffix:f0f", &[0x12, 0x3f, 0x0f]));
+        assert!(!matches("suffix:f0f", &[0x12, 0x3f, 0x1f]));
+        assert!(matches("suffix:.", &[0x12]));
+
+        assert!(matches("bits:12", &[0x00, 0x0f]));
+        assert!(!matches("bits:13", &[0x00, 0x0f]));
+        assert!(matches("bits:16", &[0x00, 0x00, 0x80]));
+
+        assert_eq!(
+            "prefix:0A.f".parse::<Predicate>().unwrap().to_string(),
+            "prefix:0a.f"
+        );
+        assert!("prefix:0g".parse::<Predicate>().is_err());
+        assert!("zeros".parse::<Predicate>().is_err());
+        let fits = |spec: &str, hasher: &dyn Hasher| {
+            let predicate = spec.parse::<Predicate>().unwrap();
+            predicate.check_digest_len(hasher.digest_len()).is_ok()
+        };
+        assert!(fits("zeros:32", &Md5));
+        assert!(!fits("zeros:33", &Md5));
+        assert!(fits("zeros:33", &Sha1));
+        assert!(!fits(&format!("suffix:{}", "f".repeat(41)), &Sha1));
+        assert!(fits("bits:128", &Md5));
+        assert!(!fits("bits:129", &Md5));
+        assert!(fits("bits:256", &Sha256));
+        let options = |args: &[&str]| Options::from_args(args.iter().map(|a| a.to_string()));
+        assert!(options(&["--predicate", "zeros:33"]).is_err());
+        assert!(options(&["--predicate", "zeros:33", "--hash", "sha256"]).is_ok());
+        assert!("length:3".parse::<Predicate>().is_err());
+    }
+    #[test]
+    fn test_mine_from() {
+        let threads = NonZeroUsize::new(3).unwrap();
+        let predicate = Predicate::leading_zeros(4);
+        let expected = mine_md5("abcdef", &predicate, threads);
+        let reports = Mutex::new(vec![]);
+        for first in [0, 1, expected - 1, expected] {
+            let nonce = mine_from("abcdef", &Md5, &predicate, threads, first, &|n| {
+                reports.lock().unwrap().push(n)
+            });
+            assert_eq!(nonce, expected);
+        }
+        assert_eq!(reports.into_inner().unwrap().last(), Some(&expected));
+
+        let mut progress = Progress {
+            scanned: 0,
+            reported: 0,
+            done: BTreeSet::new(),
+        };
+        for chunk in (1..PROGRESS_CHUNKS).rev() {
+            assert_eq!(progress.complete(chunk), None);
+        }
+        assert_eq!(progress.complete(0), Some(PROGRESS_CHUNKS));
+        assert_eq!(progress.complete(PROGRESS_CHUNKS + 1), None);
+    }
+    #[test]
+    fn test_checkpoints() {
+        let path = env::temp_dir().join(format!("day4-checkpoint-{}.json", std::process::id()));
+        let threads = NonZeroUsize::new(2).unwrap();
+        let zeros = Predicate::leading_zeros(4);
+        let bits = Predicate::LeadingZeroBits(12);
+
+        let mut checkpoints = Checkpoints::load(path.clone(), true).unwrap();
+        let nonce = checkpoints.mine("abcdef", &Md5, &zeros, threads).unwrap();
+        checkpoints.record("abcdef", &Md5, &bits, 5).unwrap();
+        checkpoints.record("abcdef", &Md5, &bits, 7).unwrap();
+
+        // As if the search was interrupted after scanning half way.
+        let mut checkpoints = Checkpoints::load(path.clone(), true).unwrap();
+        assert_eq!(checkpoints.entries.len(), 2);
+        assert_eq!(checkpoints.scanned("abcdef", &Md5, &zeros), nonce);
+        assert_eq!(checkpoints.scanned("abcdef", &Md5, &bits), 7);
+        assert_eq!(checkpoints.scanned("pqrstuv", &Md5, &zeros), 0);
+        assert_eq!(checkpoints.scanned("abcdef", &Sha1, &zeros), 0);
+        checkpoints
+            .record("abcdef", &Md5, &zeros, nonce / 2)
+            .unwrap();
+        let mut checkpoints = Checkpoints::load(path.clone(), true).unwrap();
+        assert_eq!(
+            checkpoints.mine("abcdef", &Md5, &zeros, threads).unwrap(),
+            nonce
+        );
+
+        assert!(Checkpoints::load(path.clone(), false)
+            .unwrap()
+            .entries
+            .is_empty());
+        fs::write(
+            &path,
+            "[{\"key\": \"abcdef\", \"predicate\": \"bits:12\", \"scanned\": 3}]",
+        )
+        .unwrap();
+        let checkpoints = Checkpoints::load(path.clone(), true).unwrap();
+        assert_eq!(checkpoints.scanned("abcdef", &Md5, &bits), 3);
+        fs::write(&path, "[{\"key\": \"abcdef\"}]").unwrap();
+        assert!(Checkpoints::load(path.clone(), true).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+    #[test]
+    fn test_mine_with_predicates() {
+        let threads = NonZeroUsize::new(2).unwrap();
+        for spec in ["suffix:000", "prefix:.a.b", "bits:14"] {
+            let predicate = spec.parse::<Predicate>().unwrap();
+            let nonce = mine_md5("abcdef", &predicate, threads);
+            let expected = (0..)
+                .find(|i| predicate.matches(&md5::compute(format!("abcdef{}", i)).0))
+                .unwrap();
+            assert_eq!(nonce, expected, "{}", spec);
+        }
+    }
+    #[test]
+    fn test_hashers() {
+        let threads = NonZeroUsize::new(2).unwrap();
+        let predicate = Predicate::leading_zeros(4);
+        for hasher in HASHERS {
+            let nonce = mine_from("abcdef", hasher, &predicate, threads, 0, &|_| {});
+            let mut digest = vec![];
+            let expected = (0..)
+                .find(|i| {
+                    hasher.hash(format!("abcdef{}", i).as_bytes(), &mut digest);
+                    predicate.matches(&digest)
+                })
+                .unwrap();
+            assert_eq!(nonce, expected, "{}", hasher.name());
+        }
+        let mut digest = vec![];
+        Sha1.hash(b"abc", &mut digest);
+        assert_eq!(digest[..4], [0xa9, 0x99, 0x3e, 0x36]);
+        Sha256.hash(b"abc", &mut digest);
+        assert_eq!(digest.len(), 32);
+        assert_eq!(digest[..4], [0xba, 0x78, 0x16, 0xbf]);
+
+        let options = |args: &[&str]| Options::from_args(args.iter().map(|a| a.to_string()));
+        assert_eq!(
+            options(&["--hash", "sha256"]).unwrap().hasher.name(),
+            "sha256"
+        );
+        assert!(options(&["--hash", "sha3"]).is_err());
+        assert!(options(&["--hash", "sha1", "--single-threaded"]).is_err());
+    }
+    #[test]
+    fn test_scan() {
+        let predicate = Predicate::leading_zeros(3);
+        let mut digest = vec![];
+        let expected = (1000..35_000)
+            .filter_map(|nonce| {
+                Sha256.hash(format!("abcdef{}", nonce).as_bytes(), &mut digest);
+                predicate.matches(&digest).then(|| (nonce, hex(&digest)))
+            })
+            .collect::<Vec<_>>();
+        assert!(expected.len() > 1);
+        for threads in [1, 3] {
+            let threads = NonZeroUsize::new(threads).unwrap();
+            let mut found = vec![];
+            scan(
+                "abcdef\n",
+                &Sha256,
+                &predicate,
+                threads,
+                1000..35_000,
+                |nonce, digest| found.push((nonce, hex(digest))),
+            );
+            assert_eq!(found, expected);
+        }
+        let mut found = vec![];
+        scan(
+            "abcdef",
+            &Md5,
+            &predicate,
+            NonZeroUsize::MIN,
+            7..7,
+            |nonce, _| found.push(nonce),
+        );
+        assert!(found.is_empty());
+        let mut found = vec![];
+        let any = Predicate::leading_zeros(0);
+        let threads = NonZeroUsize::new(2).unwrap();
+        scan(
+            "abcdef",
+            &Md5,
+            &any,
+            threads,
+            u64::MAX - 5..u64::MAX,
+            |nonce, _| found.push(nonce),
+        );
+        assert_eq!(found, (u64::MAX - 5..u64::MAX).collect::<Vec<_>>());
+        let mut found = 0;
+        scan("abcdef", &Md5, &any, threads, 0..CHUNK * 20 + 3, |_, _| {
+            found += 1
+        });
+        assert_eq!(found, CHUNK * 20 + 3);
+        assert_eq!(hex(&[0x00, 0x0a, 0xff]), "000aff");
+
+        let options = |args: &[&str]| Options::from_args(args.iter().map(|a| a.to_string()));
+        assert_eq!(options(&["--all", "5..10"]).unwrap().range, Some(5..10));
+        assert!(options(&["--all", "10..5"]).is_err());
+        assert!(options(&["--all", "5-10"]).is_err());
+        assert!(options(&["--all", "0..9", "--checkpoint", "c.json"]).is_err());
+    }
 }
@@ -1,19 +1,120 @@
-use std::io;
+use std::{
+    collections::HashMap,
+    env, error, fmt, fs,
+    io::{self, BufRead, Write},
+    mem,
+    num::NonZeroUsize,
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Mutex,
+    },
+    thread,
+};
+
+use code_2015::{check::InputProblem, trace};
+use serde_json::{json, Map, Value};
+use unicode_segmentation::UnicodeSegmentation;
 
 fn main() -> io::Result<()> {
-    let lines: Result<Vec<String>, _> = io::stdin().lines().collect();
+    trace::init();
+    let options = match Options::from_args(env::args().skip(1)) {
+        Ok(o) => o,
+        Err(e) => {
+            println!("{}", e);
+            println!(
+                "usage: day5 [--rules <file>] [--explain] [--explain-json <file|->] \
+                 [--segment <chars|graphemes>] | [--stream [--threads <n>]]"
+            );
+            println!(
+                "       file: lines of <name>: <rule> <rule>... with rules vowels:<n>[:<letters>], \
+                 forbid:<s1>,<s2>,..., repeat:<gap> or twice:<length>"
+            );
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, e));
+        }
+    };
+    if options.stream {
+        let (nice, new_nice) =
+            trace::solve_all(5, || classify_stream(io::stdin().lock(), options.threads))?;
+        println!("Has {} nice strings", nice);
+        println!("Has new {} nice strings", new_nice);
+        return Ok(());
+    }
+    let rule_sets = match options.rules.as_ref() {
+        Some(path) => {
+            let content = fs::read_to_string(path)?;
+            match parse_rule_sets(&content) {
+                Ok(r) => Some(r),
+                Err(e) => {
+                    println!("{}", e);
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, e));
+                }
+            }
+        }
+        // Only the rule engine splits strings into graphemes.
+        None if options.segmentation == Segmentation::Graphemes => {
+            Some(parse_rule_sets(PUZZLE_RULES).unwrap())
+        }
+        None => None,
+    };
+    let segmentation = options.segmentation;
+    let lines: Result<Vec<String>, _> = trace::parse(5, || io::stdin().lines().collect());
     match lines {
         Ok(input) => {
-            let mut num = input
-                .iter()
-                .filter(|l| check_nice_string(l.as_str()))
-                .count();
+            if options.explain || options.explain_json.is_some() {
+                let puzzle_rules;
+                let rule_sets = match rule_sets.as_ref() {
+                    Some(r) => r,
+                    None => {
+                        puzzle_rules = parse_rule_sets(PUZZLE_RULES).unwrap();
+                        &puzzle_rules
+                    }
+                };
+                if options.explain {
+                    write_explanations(io::stdout().lock(), &input, rule_sets, segmentation)?;
+                }
+                let json = || {
+                    let mut json = serde_json::to_string_pretty(&explain_json(
+                        &input,
+                        rule_sets,
+                        segmentation,
+                    ))?;
+                    json.push('\n');
+                    Ok::<_, io::Error>(json)
+                };
+                match options.explain_json.as_deref() {
+                    Some("-") => print!("{}", json()?),
+                    Some(path) => fs::write(path, json()?)?,
+                    None => {}
+                }
+                // The verdicts replace the counts, so the JSON can go to stdout.
+                return Ok(());
+            }
+            if let Some(rule_sets) = rule_sets {
+                for rule_set in rule_sets.iter() {
+                    let num = trace::solve_all(5, || {
+                        input
+                            .iter()
+                            .filter(|l| rule_set.is_nice(l, segmentation))
+                            .count()
+                    });
+                    println!("Has {} {} strings", num, rule_set.name);
+                }
+                return Ok(());
+            }
+            let mut classifier = Classifier::default();
+            let mut num = trace::solve(5, 1, || {
+                input.iter().filter(|l| classifier.classify(l).nice).count()
+            });
             println!("Has {} nice strings", num);
 
-            num = input
-                .iter()
-                .filter(|l| check_new_nice_string(l.as_str()))
-                .count();
+            num = trace::solve(5, 2, || {
+                input
+                    .iter()
+                    .filter(|l| classifier.classify(l).new_nice)
+                    .count()
+            });
             println!("Has new {} nice strings", num);
         }
         Err(e) => println!("read input lines faied, {}", e),
@@ -21,6 +122,93 @@ fn main() -> io::Result<()> {
     Ok(())
 }
 
+struct Options {
+    rules: Option<PathBuf>,
+    explain: bool,
+    explain_json: Option<String>,
+    segmentation: Segmentation,
+    /// Classifies stdin line by line on `threads` threads instead of
+    /// collecting it.
+    stream: bool,
+    threads: NonZeroUsize,
+}
+
+impl Options {
+    fn from_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options {
+            rules: None,
+            explain: false,
+            explain_json: None,
+            segmentation: Segmentation::Chars,
+            stream: false,
+            threads: thread::available_parallelism().unwrap_or(NonZeroUsize::MIN),
+        };
+        let mut threads = false;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--rules" => {
+                    let path = args.next().ok_or("missing file after --rules")?;
+                    options.rules = Some(path.into());
+                }
+                "--explain" => options.explain = true,
+                "--explain-json" => {
+                    let path = args.next().ok_or("missing file after --explain-json")?;
+                    options.explain_json = Some(path);
+                }
+                "--segment" => {
+                    let segmentation = args.next().ok_or("missing unit after --segment")?;
+                    options.segmentation = segmentation.parse()?;
+                }
+                "--stream" => options.stream = true,
+                "--threads" => {
+                    let n = args.next().ok_or("missing count after --threads")?;
+                    options.threads = n
+                        .parse()
+                        .map_err(|_| format!("invalid thread count: \"{}\"", n))?;
+                    threads = true;
+                }
+                _ => return Err(format!("unknown option: \"{}\"", arg)),
+            }
+        }
+        if threads && !options.stream {
+            return Err("--threads needs --stream".into());
+        }
+        if options.stream
+            && (options.rules.is_some()
+                || options.explain
+                || options.explain_json.is_some()
+                || options.segmentation != Segmentation::Chars)
+        {
+            return Err("--stream only counts the puzzle's nice strings".into());
+        }
+        if options.explain && options.explain_json.as_deref() == Some("-") {
+            return Err("--explain and --explain-json - can't both write to stdout".into());
+        }
+        Ok(options)
+    }
+}
+
+pub fn part_one(input: &str) -> Result<String, Box<dyn error::Error>> {
+    let mut classifier = Classifier::default();
+    Ok(input
+        .lines()
+        .filter(|l| classifier.classify(l).nice)
+        .count()
+        .to_string())
+}
+
+pub fn part_two(input: &str) -> Result<String, Box<dyn error::Error>> {
+    let mut classifier = Classifier::default();
+    Ok(input
+        .lines()
+        .filter(|l| classifier.classify(l).new_nice)
+        .count()
+        .to_string())
+}
+
+/// The puzzle's rules checked one by one, kept as the reference `Classifier`
+/// is tested against.
+#[cfg(test)]
 fn check_nice_string(input: &str) -> bool {
     if input
         .chars()
@@ -46,6 +234,7 @@ fn check_nice_string(input: &str) -> bool {
     return false;
 }
 
+#[cfg(test)]
 fn check_new_nice_string(input: &str) -> bool {
     if has_repeat_char(input) {
         return repeat_without_overlap(input);
@@ -54,16 +243,461 @@ fn check_new_nice_string(input: &str) -> bool {
     return false;
 }
 
+#[cfg(test)]
 fn repeat_without_overlap(input: &str) -> bool {
-    for i in 0..input.len() - 3 {
-        let seed = &input[i..i + 2];
-        if input[i + 2..].contains(seed) {
+    let chars = input.chars().collect::<Vec<char>>();
+    for i in 0..chars.len().saturating_sub(3) {
+        let seed = &chars[i..i + 2];
+        if chars[i + 2..].windows(2).any(|w| w == seed) {
             return true;
         }
     }
     return false;
 }
 
+/// Both of the puzzle's verdicts on a string.
+#[derive(Debug, PartialEq)]
+struct Class {
+    nice: bool,
+    new_nice: bool,
+}
+
+/// Decides `check_nice_string` and `check_new_nice_string` in a single pass
+/// over the characters. Where each pair first occurs is kept between strings
+/// to reuse the allocations, in a table stamped with the string number for
+/// pairs of lowercase letters, the rest in a map.
+struct Classifier {
+    generation: usize,
+    letter_pairs: Vec<(usize, usize)>,
+    pairs: HashMap<(char, char), usize>,
+}
+
+impl Default for Classifier {
+    fn default() -> Self {
+        Classifier {
+            generation: 0,
+            letter_pairs: vec![(0, 0); 26 * 26],
+            pairs: HashMap::new(),
+        }
+    }
+}
+
+impl Classifier {
+    /// Where the pair first started in the current string, after recording
+    /// it at `at` if it's new.
+    fn first_pair(&mut self, pair: (char, char), at: usize) -> usize {
+        match pair {
+            ('a'..='z', 'a'..='z') => {
+                let index =
+                    (pair.0 as usize - 'a' as usize) * 26 + (pair.1 as usize - 'a' as usize);
+                let entry = &mut self.letter_pairs[index];
+                if entry.0 != self.generation {
+                    *entry = (self.generation, at);
+                }
+                entry.1
+            }
+            _ => *self.pairs.entry(pair).or_insert(at),
+        }
+    }
+
+    fn classify(&mut self, input: &str) -> Class {
+        self.generation += 1;
+        self.pairs.clear();
+        let mut vowels = 0;
+        let mut forbidden = false;
+        let mut double = false;
+        let mut gap_repeat = false;
+        let mut twice = false;
+        let mut before_previous = None;
+        let mut previous = None;
+        for (i, c) in input.chars().enumerate() {
+            vowels += usize::from(matches!(c, 'a' | 'e' | 'i' | 'o' | 'u'));
+            if let Some(p) = previous {
+                // The forbidden pairs are all a letter and the next one.
+                forbidden |= matches!(p, 'a' | 'c' | 'p' | 'x') && c as u32 == p as u32 + 1;
+                double |= p == c;
+                // Only the first pair is kept, the one least likely to overlap.
+                twice = twice || self.first_pair((p, c), i - 1) + 2 < i;
+            }
+            gap_repeat |= before_previous == Some(c);
+            before_previous = previous;
+            previous = Some(c);
+        }
+        Class {
+            nice: vowels >= 3 && !forbidden && double,
+            new_nice: twice && gap_repeat,
+        }
+    }
+}
+
+/// Lines handed to a classifying thread at once.
+const BATCH: usize = 4096;
+
+/// Counts the nice and new nice strings among the lines of `reader`,
+/// classified in batches on `threads` threads while the rest is being read.
+fn classify_stream(reader: impl BufRead, threads: NonZeroUsize) -> io::Result<(usize, usize)> {
+    let nice = AtomicUsize::new(0);
+    let new_nice = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::sync_channel::<Vec<String>>(threads.get() * 2);
+    let receiver = Mutex::new(receiver);
+    thread::scope(|s| {
+        for _ in 0..threads.get() {
+            s.spawn(|| {
+                let mut classifier = Classifier::default();
+                while let Ok(batch) = receiver.lock().unwrap().recv() {
+                    let (mut n, mut m) = (0, 0);
+                    for line in batch.iter() {
+                        let class = classifier.classify(line);
+                        n += usize::from(class.nice);
+                        m += usize::from(class.new_nice);
+                    }
+                    nice.fetch_add(n, Ordering::Relaxed);
+                    new_nice.fetch_add(m, Ordering::Relaxed);
+                }
+            });
+        }
+        // Owning the sender here stops the threads however reading ends.
+        let sender = sender;
+        let mut batch = Vec::with_capacity(BATCH);
+        for line in reader.lines() {
+            batch.push(line?);
+            if batch.len() == BATCH {
+                let full = mem::replace(&mut batch, Vec::with_capacity(BATCH));
+                if sender.send(full).is_err() {
+                    break;
+                }
+            }
+        }
+        if !batch.is_empty() {
+            let _ = sender.send(batch);
+        }
+        Ok::<_, io::Error>(())
+    })?;
+    Ok((nice.into_inner(), new_nice.into_inner()))
+}
+
+/// The puzzle's own rule sets, written as a `--rules` file.
+const PUZZLE_RULES: &str = "\
+nice: vowels:3 forbid:ab,cd,pq,xy repeat:0
+new-nice: twice:2 repeat:1
+";
+
+/// One condition a nice string has to meet.
+#[derive(Debug, PartialEq)]
+enum Rule {
+    /// At least `at_least` characters out of `letters`.
+    Vowels { letters: String, at_least: usize },
+    /// None of the substrings occurs.
+    Forbid(Vec<String>),
+    /// A character occurs again with exactly this many characters in between.
+    Repeat(usize),
+    /// A run of this many characters occurs twice without overlapping.
+    Twice(usize),
+}
+
+/// What the rules count as one character.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Segmentation {
+    /// Unicode scalar values, `char`s.
+    Chars,
+    /// Extended grapheme clusters, so "e\u{301}" is a single "é".
+    Graphemes,
+}
+
+impl Segmentation {
+    fn split<'a>(&self, input: &'a str) -> Vec<&'a str> {
+        match self {
+            Segmentation::Chars => input
+                .char_indices()
+                .map(|(i, c)| &input[i..i + c.len_utf8()])
+                .collect(),
+            Segmentation::Graphemes => input.graphemes(true).collect(),
+        }
+    }
+}
+
+impl FromStr for Segmentation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "chars" => Ok(Segmentation::Chars),
+            "graphemes" => Ok(Segmentation::Graphemes),
+            _ => Err(format!("invalid segmentation: \"{}\"", s)),
+        }
+    }
+}
+
+impl Rule {
+    fn check(&self, input: &str, segmentation: Segmentation) -> bool {
+        self.explain(input, segmentation).passed()
+    }
+
+    /// Finds what decides the rule for `input`, positions count characters
+    /// from 0. Substrings only match on character boundaries.
+    fn explain(&self, input: &str, segmentation: Segmentation) -> Verdict {
+        let chars = segmentation.split(input);
+        match self {
+            Rule::Vowels { letters, at_least } => {
+                let letters = segmentation.split(letters);
+                Verdict::Vowels {
+                    found: chars.iter().filter(|c| letters.contains(c)).count(),
+                    required: *at_least,
+                }
+            }
+            Rule::Forbid(substrings) => Verdict::Forbidden(
+                substrings
+                    .iter()
+                    .filter_map(|s| {
+                        let run = segmentation.split(s);
+                        let at = chars.windows(run.len()).position(|w| w == run)?;
+                        Some((at, s))
+                    })
+                    .min()
+                    .map(|(at, s)| (s.clone(), at)),
+            ),
+            Rule::Repeat(gap) => Verdict::Repeat(
+                chars
+                    .iter()
+                    .zip(chars.iter().skip(gap + 1))
+                    .position(|(a, b)| a == b)
+                    .map(|i| (chars[i..=i + gap + 1].concat(), i)),
+            ),
+            Rule::Twice(length) => {
+                Verdict::Twice(chars.windows(*length).enumerate().find_map(|(i, run)| {
+                    let rest = chars.get(i + length..)?;
+                    let j = rest.windows(*length).position(|w| w == run)?;
+                    Some((run.concat(), i, i + length + j))
+                }))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rule::Vowels { letters, at_least } => match letters.as_str() {
+                "aeiou" => write!(f, "vowels:{}", at_least),
+                _ => write!(f, "vowels:{}:{}", at_least, letters),
+            },
+            Rule::Forbid(substrings) => write!(f, "forbid:{}", substrings.join(",")),
+            Rule::Repeat(gap) => write!(f, "repeat:{}", gap),
+            Rule::Twice(length) => write!(f, "twice:{}", length),
+        }
+    }
+}
+
+/// What a rule found in a string, one variant per `Rule`.
+#[derive(Debug, PartialEq)]
+enum Verdict {
+    Vowels {
+        found: usize,
+        required: usize,
+    },
+    /// The forbidden substring that occurs first and where.
+    Forbidden(Option<(String, usize)>),
+    /// The first character repeated with its gap, e.g. "xyx", and where.
+    Repeat(Option<(String, usize)>),
+    /// The first run found twice and where both occurrences start.
+    Twice(Option<(String, usize, usize)>),
+}
+
+impl Verdict {
+    fn passed(&self) -> bool {
+        match self {
+            Verdict::Vowels { found, required } => found >= required,
+            Verdict::Forbidden(found) => found.is_none(),
+            Verdict::Repeat(found) => found.is_some(),
+            Verdict::Twice(found) => found.is_some(),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        match self {
+            Verdict::Vowels { found, required } => json!({"found": found, "required": required}),
+            Verdict::Forbidden(found) | Verdict::Repeat(found) => match found {
+                Some((s, at)) => json!({"found": s, "at": at}),
+                None => json!({"found": null}),
+            },
+            Verdict::Twice(found) => match found {
+                Some((s, first, second)) => json!({"found": s, "at": [first, second]}),
+                None => json!({"found": null}),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Vowels { found, required } => {
+                write!(f, "found {} vowels, needs {}", found, required)
+            }
+            Verdict::Forbidden(None) => write!(f, "no forbidden substring"),
+            Verdict::Repeat(None) => write!(f, "no repeated character"),
+            Verdict::Twice(None) => write!(f, "no run found twice"),
+            Verdict::Forbidden(Some((s, at))) | Verdict::Repeat(Some((s, at))) => {
+                write!(f, "\"{}\" at {}", s, at)
+            }
+            Verdict::Twice(Some((s, first, second))) => {
+                write!(f, "\"{}\" at {} and {}", s, first, second)
+            }
+        }
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid rule: \"{}\"", s);
+        let (kind, args) = s.split_once(':').ok_or_else(invalid)?;
+        match kind {
+            "vowels" => {
+                let (at_least, letters) = args.split_once(':').unwrap_or((args, "aeiou"));
+                let at_least = at_least.parse().map_err(|_| invalid())?;
+                if letters.is_empty() {
+                    return Err(invalid());
+                }
+                Ok(Rule::Vowels {
+                    letters: letters.to_string(),
+                    at_least,
+                })
+            }
+            "forbid" => {
+                let substrings = args.split(',').map(String::from).collect::<Vec<_>>();
+                if substrings.iter().any(|s| s.is_empty()) {
+                    return Err(invalid());
+                }
+                Ok(Rule::Forbid(substrings))
+            }
+            "repeat" => Ok(Rule::Repeat(args.parse().map_err(|_| invalid())?)),
+            "twice" => match args.parse() {
+                Ok(length) if length > 0 => Ok(Rule::Twice(length)),
+                _ => Err(invalid()),
+            },
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// A named list of rules, all of which a nice string meets.
+struct RuleSet {
+    name: String,
+    rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    fn is_nice(&self, input: &str, segmentation: Segmentation) -> bool {
+        self.rules.iter().all(|r| r.check(input, segmentation))
+    }
+
+    fn explain(&self, input: &str, segmentation: Segmentation) -> Vec<(&Rule, Verdict)> {
+        self.rules
+            .iter()
+            .map(|r| (r, r.explain(input, segmentation)))
+            .collect()
+    }
+}
+
+/// Writes every rule's verdict on every line, grouped by rule set.
+fn write_explanations(
+    mut out: impl Write,
+    input: &[String],
+    rule_sets: &[RuleSet],
+    segmentation: Segmentation,
+) -> io::Result<()> {
+    for (i, line) in input.iter().enumerate() {
+        writeln!(out, "{} {}", i + 1, line)?;
+        for rule_set in rule_sets {
+            let verdicts = rule_set.explain(line, segmentation);
+            let nice = verdicts.iter().all(|(_, v)| v.passed());
+            let status = if nice { "nice" } else { "naughty" };
+            writeln!(out, "  {}: {}", rule_set.name, status)?;
+            for (rule, verdict) in verdicts {
+                let status = if verdict.passed() { "pass" } else { "fail" };
+                writeln!(out, "    {} {}  {}", status, rule, verdict)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The same as `write_explanations` as an array with one object per line.
+fn explain_json(input: &[String], rule_sets: &[RuleSet], segmentation: Segmentation) -> Value {
+    let lines = input.iter().enumerate().map(|(i, line)| {
+        let mut sets = Map::new();
+        for rule_set in rule_sets {
+            let verdicts = rule_set.explain(line, segmentation);
+            let rules = verdicts
+                .iter()
+                .map(|(rule, verdict)| {
+                    let mut value = verdict.to_json();
+                    value["rule"] = json!(rule.to_string());
+                    value["passed"] = json!(verdict.passed());
+                    value
+                })
+                .collect::<Vec<_>>();
+            let nice = verdicts.iter().all(|(_, v)| v.passed());
+            sets.insert(rule_set.name.clone(), json!({"nice": nice, "rules": rules}));
+        }
+        json!({"line": i + 1, "input": line, "rule_sets": sets})
+    });
+    Value::Array(lines.collect())
+}
+
+/// Parses lines of `<name>: <rule> <rule>...`, skipping blank lines and
+/// lines starting with `#`.
+fn parse_rule_sets(content: &str) -> Result<Vec<RuleSet>, String> {
+    let mut rule_sets = vec![];
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, rules) = line
+            .split_once(':')
+            .ok_or_else(|| format!("line {}: missing rule set name", i + 1))?;
+        let rules = rules
+            .split_whitespace()
+            .map(|r| r.parse())
+            .collect::<Result<Vec<Rule>, _>>()
+            .map_err(|e| format!("line {}: {}", i + 1, e))?;
+        rule_sets.push(RuleSet {
+            name: name.trim().to_string(),
+            rules,
+        });
+    }
+    if rule_sets.is_empty() {
+        return Err("no rule sets".into());
+    }
+    Ok(rule_sets)
+}
+
+pub fn check_input(input: &str) -> Vec<InputProblem> {
+    let mut problems = vec![];
+    for (i, line) in input.lines().enumerate() {
+        if line.chars().count() < 3 {
+            problems.push(InputProblem::at_line(
+                i + 1,
+                format!("string: \"{}\" is shorter than 3 characters", line),
+            ));
+        }
+        for (j, c) in line.chars().enumerate() {
+            if !c.is_ascii_lowercase() {
+                problems.push(InputProblem::at(
+                    i + 1,
+                    j + 1,
+                    format!("unexpected character {:?}", c),
+                ));
+            }
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
 fn has_repeat_char(input: &str) -> bool {
     for r in input.chars().zip(input.chars().skip(2)) {
         if r.0 == r.1 {
@@ -91,4 +725,257 @@ mod tests {
         assert_eq!(check_new_nice_string("uurcxstgmygtbstg"), false);
         assert_eq!(check_new_nice_string("ieodomkazucvgmuy"), false);
     }
+    #[test]
+    fn test_rule_sets() {
+        let rule_sets = parse_rule_sets(PUZZLE_RULES).unwrap();
+        let [nice, new_nice] = &rule_sets[..] else {
+            panic!("expected 2 rule sets");
+        };
+        assert_eq!(nice.name, "nice");
+        assert_eq!(new_nice.name, "new-nice");
+        for input in [
+            "ugknbfddgicrmopn",
+            "aaa",
+            "jchzalrnumimnmhp",
+            "haegwjzuvuyypxyu",
+            "dvszwmarrgswjxmb",
+            "qjhvhtzxzqqjkmpb",
+            "xxyxx",
+            "uurcxstgmygtbstg",
+            "ieodomkazucvgmuy",
+            "aaaa",
+        ] {
+            assert_eq!(
+                nice.is_nice(input, Segmentation::Chars),
+                check_nice_string(input),
+                "{}",
+                input
+            );
+            assert_eq!(
+                new_nice.is_nice(input, Segmentation::Chars),
+                check_new_nice_string(input),
+                "{}",
+                input
+            );
+        }
+
+        let custom = parse_rule_sets("# custom\n\nstrict: vowels:2:y forbid:zz twice:3\n").unwrap();
+        assert_eq!(
+            custom[0].rules,
+            [
+                Rule::Vowels {
+                    letters: "y".to_string(),
+                    at_least: 2
+                },
+                Rule::Forbid(vec!["zz".to_string()]),
+                Rule::Twice(3),
+            ]
+        );
+        assert!(custom[0].is_nice("yabcyabc", Segmentation::Chars));
+        assert!(!custom[0].is_nice("yabcyb", Segmentation::Chars));
+        assert!(!custom[0].is_nice("yabcyabczz", Segmentation::Chars));
+        assert!(Rule::Repeat(2).check("abxa", Segmentation::Chars));
+        assert!(!Rule::Repeat(2).check("ab", Segmentation::Chars));
+
+        assert!(parse_rule_sets("").is_err());
+        assert!(parse_rule_sets("vowels:3").is_err());
+        assert!(parse_rule_sets("nice: vowels:x").is_err());
+        assert!(parse_rule_sets("nice: forbid:ab,").is_err());
+        assert!(parse_rule_sets("nice: twice:0").is_err());
+        assert!(parse_rule_sets("nice: palindrome:3").is_err());
+    }
+    #[test]
+    fn test_explain() {
+        let rule_sets = parse_rule_sets(PUZZLE_RULES).unwrap();
+        let verdicts = |set: usize, input: &str| {
+            rule_sets[set]
+                .explain(input, Segmentation::Chars)
+                .into_iter()
+                .map(|(_, v)| v)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            verdicts(0, "haegwjzuvuyypxyu"),
+            [
+                Verdict::Vowels {
+                    found: 5,
+                    required: 3
+                },
+                Verdict::Forbidden(Some(("xy".to_string(), 13))),
+                Verdict::Repeat(Some(("yy".to_string(), 10))),
+            ]
+        );
+        assert_eq!(
+            verdicts(0, "dvszwmarrgswjxmb")[0],
+            Verdict::Vowels {
+                found: 1,
+                required: 3
+            }
+        );
+        assert_eq!(
+            verdicts(1, "uurcxstgmygtbstg"),
+            [
+                Verdict::Twice(Some(("st".to_string(), 5, 13))),
+                Verdict::Repeat(None),
+            ]
+        );
+        assert_eq!(
+            verdicts(1, "qjhvhtzxzqqjkmpb"),
+            [
+                Verdict::Twice(Some(("qj".to_string(), 0, 10))),
+                Verdict::Repeat(Some(("hvh".to_string(), 2))),
+            ]
+        );
+        assert_eq!(
+            Rule::Forbid(vec!["cd".to_string(), "ab".to_string()])
+                .explain("xabcd", Segmentation::Chars),
+            Verdict::Forbidden(Some(("ab".to_string(), 1)))
+        );
+        for rule in [
+            "vowels:3",
+            "vowels:2:y",
+            "forbid:ab,cd",
+            "repeat:1",
+            "twice:2",
+        ] {
+            assert_eq!(rule.parse::<Rule>().unwrap().to_string(), rule);
+        }
+
+        let input = vec!["xxzxx".to_string()];
+        let mut text = vec![];
+        write_explanations(&mut text, &input, &rule_sets, Segmentation::Chars).unwrap();
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "1 xxzxx
+  nice: naughty
+    fail vowels:3  found 0 vowels, needs 3
+    pass forbid:ab,cd,pq,xy  no forbidden substring
+    pass repeat:0  \"xx\" at 0
+  new-nice: nice
+    pass twice:2  \"xx\" at 0 and 3
+    pass repeat:1  \"xzx\" at 1
+"
+        );
+        let json = explain_json(&input, &rule_sets, Segmentation::Chars);
+        assert_eq!(json[0]["rule_sets"]["nice"]["nice"], false);
+        assert_eq!(
+            json[0]["rule_sets"]["new-nice"]["rules"][0],
+            json!({"rule": "twice:2", "passed": true, "found": "xx", "at": [0, 3]})
+        );
+        assert_eq!(
+            json[0]["rule_sets"]["nice"]["rules"][1],
+            json!({"rule": "forbid:ab,cd,pq,xy", "passed": true, "found": null})
+        );
+
+        let options = |args: &[&str]| Options::from_args(args.iter().map(|a| a.to_string()));
+        assert!(options(&["--explain", "--explain-json", "-"]).is_err());
+        assert!(options(&["--explain-json", "-"]).is_ok());
+        assert!(options(&["--explain", "--explain-json", "out.json"]).is_ok());
+    }
+    #[test]
+    fn test_short_and_unicode_strings() {
+        for input in ["", "a", "ab", "aa", "é", "éé", "日本"] {
+            assert!(!check_nice_string(input), "{}", input);
+            assert!(!check_new_nice_string(input), "{}", input);
+            assert!(!repeat_without_overlap(input), "{}", input);
+        }
+        assert!(check_nice_string("aeé日日i"));
+        assert!(repeat_without_overlap("日本日本"));
+        assert!(!repeat_without_overlap("日日日"));
+        assert!(check_new_nice_string("éxéaéx"));
+        assert_eq!(check_input("é").len(), 2);
+
+        let rule_sets = parse_rule_sets(PUZZLE_RULES).unwrap();
+        for segmentation in [Segmentation::Chars, Segmentation::Graphemes] {
+            for input in ["", "a", "ab", "aa", "é", "éé", "日本"] {
+                for rule_set in rule_sets.iter() {
+                    assert!(!rule_set.is_nice(input, segmentation), "{}", input);
+                    rule_set.explain(input, segmentation);
+                }
+            }
+            assert!(rule_sets[1].is_nice("éxéaéx", segmentation));
+        }
+
+        // "e" followed by a combining acute accent.
+        let accent = "e\u{301}xe\u{301}";
+        let rule = |spec: &str| spec.parse::<Rule>().unwrap();
+        assert_eq!(
+            rule("repeat:1").explain(accent, Segmentation::Chars),
+            Verdict::Repeat(None)
+        );
+        assert_eq!(
+            rule("repeat:1").explain(accent, Segmentation::Graphemes),
+            Verdict::Repeat(Some((accent.to_string(), 0)))
+        );
+        assert_eq!(
+            rule("forbid:e").explain(accent, Segmentation::Chars),
+            Verdict::Forbidden(Some(("e".to_string(), 0)))
+        );
+        assert_eq!(
+            rule("forbid:e").explain(accent, Segmentation::Graphemes),
+            Verdict::Forbidden(None)
+        );
+        let vowels = rule("vowels:2:e\u{301}");
+        assert!(vowels.check(accent, Segmentation::Chars));
+        assert!(vowels.check(accent, Segmentation::Graphemes));
+        assert!(!vowels.check("e\u{301}", Segmentation::Graphemes));
+        assert_eq!(
+            rule("twice:2").explain(accent, Segmentation::Chars),
+            Verdict::Twice(Some(("e\u{301}".to_string(), 0, 3)))
+        );
+
+        assert_eq!("graphemes".parse(), Ok(Segmentation::Graphemes));
+        assert!("bytes".parse::<Segmentation>().is_err());
+    }
+    #[test]
+    fn test_classifier() {
+        let mut classifier = Classifier::default();
+        let mut state = 5u32;
+        let mut lines = vec![];
+        for i in 0..5000 {
+            let line = (0..i % 20)
+                .map(|_| {
+                    state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                    ['a', 'b', 'c', 'd', 'e', 'i', 'x', 'y', 'é'][(state >> 16) as usize % 9]
+                })
+                .collect::<String>();
+            let class = classifier.classify(&line);
+            assert_eq!(class.nice, check_nice_string(&line), "{}", line);
+            assert_eq!(class.new_nice, check_new_nice_string(&line), "{}", line);
+            lines.push(line);
+        }
+        for input in ["", "a", "aaa", "aaaa", "xxyxx", "日本日本日"] {
+            let class = classifier.classify(input);
+            assert_eq!(class.nice, check_nice_string(input), "{}", input);
+            assert_eq!(class.new_nice, check_new_nice_string(input), "{}", input);
+        }
+
+        let expected = (
+            lines.iter().filter(|l| check_nice_string(l)).count(),
+            lines.iter().filter(|l| check_new_nice_string(l)).count(),
+        );
+        assert!(expected.0 > 0 && expected.1 > 0);
+        let wordlist = lines.join("\n");
+        for threads in [1, 3] {
+            let threads = NonZeroUsize::new(threads).unwrap();
+            let counts = classify_stream(wordlist.as_bytes(), threads).unwrap();
+            assert_eq!(counts, expected);
+        }
+        assert_eq!(
+            classify_stream(&b""[..], NonZeroUsize::MIN).unwrap(),
+            (0, 0)
+        );
+        assert!(classify_stream(&b"ok\n\xff\n"[..], NonZeroUsize::MIN).is_err());
+
+        let options = |args: &[&str]| Options::from_args(args.iter().map(|a| a.to_string()));
+        assert_eq!(
+            options(&["--stream", "--threads", "2"])
+                .unwrap()
+                .threads
+                .get(),
+            2
+        );
+        assert!(options(&["--threads", "2"]).is_err());
+        assert!(options(&["--stream", "--explain"]).is_err());
+    }
 }
@@ -1,35 +1,175 @@
-use std::{fmt, io, str::FromStr};
+use std::{env, error, fmt, io, ops::Range, str::FromStr};
+
+use code_2015::{
+    check::{check_lines, sort_by_line, InputProblem},
+    report::Visual,
+    trace,
+};
 
 fn main() -> io::Result<()> {
-    match io::stdin()
-        .lines()
-        .collect::<Result<Vec<String>, _>>()?
-        .iter()
-        .map(|l| l.parse::<Instruction>())
-        .collect::<Result<Vec<Instruction>, ParseInstructionError>>()
-    {
+    trace::init();
+    let options = match Options::from_args(env::args().skip(1)) {
+        Ok(o) => o,
+        Err(e) => {
+            println!("{}", e);
+            println!("usage: day6 [--size <n>] [--engine <dense|compressed>]");
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, e));
+        }
+    };
+    match trace::parse(6, || {
+        io::stdin()
+            .lines()
+            .collect::<Result<Vec<String>, _>>()
+            .map(|lines| {
+                lines
+                    .iter()
+                    .map(|l| l.parse::<Instruction>())
+                    .collect::<Result<Vec<Instruction>, ParseInstructionError>>()
+            })
+    })? {
         Ok(ins) => {
-            {
-                let mut grid = Grid::new(1000);
-                ins.iter().for_each(|i| {
-                    grid.apply(i);
-                });
-                println!("{} lights are lit", grid.count());
-            }
+            trace::solve(6, 1, || {
+                let count = count_lights(options.engine, options.size, &ins, false);
+                println!("{} lights are lit", count);
+            });
 
-            {
-                let mut grid = Grid::new(1000);
-                ins.iter().for_each(|i| {
-                    grid.apply_brightness(i);
-                });
-                println!("total brightness is {}", grid.count());
-            }
+            trace::solve(6, 2, || {
+                let count = count_lights(options.engine, options.size, &ins, true);
+                println!("total brightness is {}", count);
+            });
         }
         Err(e) => println!("parse instructions failed. {}", e),
     }
     Ok(())
 }
 
+struct Options {
+    size: usize,
+    engine: Engine,
+}
+
+impl Options {
+    fn from_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options {
+            size: 1000,
+            engine: Engine::Dense,
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--size" => {
+                    let n = args.next().ok_or("missing size after --size")?;
+                    options.size = n
+                        .parse()
+                        .map_err(|_| format!("invalid grid size: \"{}\"", n))?;
+                }
+                "--engine" => {
+                    let engine = args.next().ok_or("missing engine after --engine")?;
+                    options.engine = engine.parse()?;
+                }
+                _ => return Err(format!("unknown option: \"{}\"", arg)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+pub fn part_one(input: &str) -> Result<String, Box<dyn error::Error>> {
+    let mut grid = Grid::new(1000);
+    for i in parse_instructions(input)? {
+        grid.apply(&i);
+    }
+    Ok(grid.count().to_string())
+}
+
+pub fn part_two(input: &str) -> Result<String, Box<dyn error::Error>> {
+    let mut grid = Grid::new(1000);
+    for i in parse_instructions(input)? {
+        grid.apply_brightness(&i);
+    }
+    Ok(grid.count().to_string())
+}
+
+pub fn visualize(input: &str) -> Result<Vec<Visual>, Box<dyn error::Error>> {
+    let instructions = parse_instructions(input)?;
+    let mut grid = Grid::new(1000);
+    let mut bright_grid = Grid::new(1000);
+    for i in instructions.iter() {
+        grid.apply(i);
+        bright_grid.apply_brightness(i);
+    }
+    Ok(vec![
+        Visual::Grid {
+            title: "Lit lights".into(),
+            width: grid.size,
+            height: grid.size,
+            values: grid.grid.concat(),
+        },
+        Visual::Grid {
+            title: "Light brightness".into(),
+            width: bright_grid.size,
+            height: bright_grid.size,
+            values: bright_grid.grid.concat(),
+        },
+    ])
+}
+
+/// How the lights are stored.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Engine {
+    /// One value per light, `Grid`.
+    Dense,
+    /// One value per block of lights changed together, `CompressedGrid`.
+    Compressed,
+}
+
+impl FromStr for Engine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dense" => Ok(Engine::Dense),
+            "compressed" => Ok(Engine::Compressed),
+            _ => Err(format!("invalid engine: \"{}\"", s)),
+        }
+    }
+}
+
+/// Lit lights, or the total brightness, of a `size` x `size` grid after
+/// all the instructions.
+fn count_lights(
+    engine: Engine,
+    size: usize,
+    instructions: &[Instruction],
+    brightness: bool,
+) -> u64 {
+    match engine {
+        Engine::Dense => {
+            let mut grid = Grid::new(size);
+            for i in instructions {
+                match brightness {
+                    true => grid.apply_brightness(i),
+                    false => grid.apply(i),
+                }
+            }
+            grid.count()
+        }
+        Engine::Compressed => {
+            let mut grid = CompressedGrid::new(size, instructions);
+            for i in instructions {
+                match brightness {
+                    true => grid.apply_brightness(i),
+                    false => grid.apply(i),
+                }
+            }
+            grid.count()
+        }
+    }
+}
+
+fn parse_instructions(input: &str) -> Result<Vec<Instruction>, ParseInstructionError> {
+    input.lines().map(|l| l.parse::<Instruction>()).collect()
+}
+
 #[derive(Debug)]
 enum Action {
     TurnOn,
@@ -81,9 +221,11 @@ struct Instruction {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ParseInstructionError;
 
+impl error::Error for ParseInstructionError {}
+
 impl fmt::Display for ParseInstructionError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        "provided string was not `true` or `false`".fmt(f)
+        "invalid light instruction".fmt(f)
     }
 }
 
@@ -118,6 +260,31 @@ impl FromStr for Instruction {
     }
 }
 
+pub fn check_input(input: &str) -> Vec<InputProblem> {
+    let (instructions, mut problems) = check_lines::<Instruction>(input);
+    for (line, i) in instructions {
+        for p in [&i.start, &i.end] {
+            if p.0 >= 1000 || p.1 >= 1000 {
+                problems.push(InputProblem::at_line(
+                    line,
+                    format!("position {},{} is outside of the 1000x1000 grid", p.0, p.1),
+                ));
+            }
+        }
+        if i.start.0 > i.end.0 || i.start.1 > i.end.1 {
+            problems.push(InputProblem::at_line(
+                line,
+                format!(
+                    "start {},{} is after end {},{}, no light is changed",
+                    i.start.0, i.start.1, i.end.0, i.end.1
+                ),
+            ));
+        }
+    }
+    sort_by_line(&mut problems);
+    problems
+}
+
 struct Grid {
     grid: Vec<Vec<u32>>,
     size: usize,
@@ -129,18 +296,23 @@ impl Grid {
         return Grid { grid, size };
     }
 
-    fn count(&self) -> u32 {
-        let mut count: u32 = 0;
+    fn count(&self) -> u64 {
+        let mut count: u64 = 0;
         for i in 0..self.size {
             for j in 0..self.size {
-                count += self.grid[i][j];
+                count += u64::from(self.grid[i][j]);
             }
         }
         return count;
     }
+    /// The lights from `start` to `end` inclusive that are on the grid.
+    fn clamp(&self, start: usize, end: usize) -> Range<usize> {
+        start..end.saturating_add(1).min(self.size)
+    }
+
     fn apply(&mut self, instruction: &Instruction) {
-        for i in instruction.start.1..=instruction.end.1 {
-            for j in instruction.start.0..=instruction.end.0 {
+        for i in self.clamp(instruction.start.1, instruction.end.1) {
+            for j in self.clamp(instruction.start.0, instruction.end.0) {
                 self.grid[i][j] = match instruction.action {
                     Action::TurnOn => 1,
                     Action::TurnOff => 0,
@@ -153,8 +325,8 @@ impl Grid {
         }
     }
     fn apply_brightness(&mut self, instruction: &Instruction) {
-        for i in instruction.start.1..=instruction.end.1 {
-            for j in instruction.start.0..=instruction.end.0 {
+        for i in self.clamp(instruction.start.1, instruction.end.1) {
+            for j in self.clamp(instruction.start.0, instruction.end.0) {
                 match instruction.action {
                     Action::TurnOn => self.grid[i][j] += 1,
                     Action::TurnOff => {
@@ -169,6 +341,88 @@ impl Grid {
     }
 }
 
+/// The same lights as `Grid`, but cut into blocks along the edges of the
+/// instructions' rectangles. Every light of a block always has the same
+/// value, so the grid only keeps one per block and its size doesn't matter.
+struct CompressedGrid {
+    /// The first column of each block, then the grid size.
+    xs: Vec<usize>,
+    /// The first row of each block, then the grid size.
+    ys: Vec<usize>,
+    /// The blocks one row after the other.
+    cells: Vec<u32>,
+}
+
+impl CompressedGrid {
+    /// Only the `instructions` can be applied to the grid, lights outside of
+    /// it are ignored.
+    fn new(size: usize, instructions: &[Instruction]) -> CompressedGrid {
+        let edges = |coordinate: fn(&Position) -> usize| {
+            let mut edges = vec![0, size];
+            for i in instructions {
+                edges.push(coordinate(&i.start).min(size));
+                edges.push(coordinate(&i.end).saturating_add(1).min(size));
+            }
+            edges.sort_unstable();
+            edges.dedup();
+            edges
+        };
+        let xs = edges(|p| p.0);
+        let ys = edges(|p| p.1);
+        let cells = vec![0; (xs.len() - 1) * (ys.len() - 1)];
+        CompressedGrid { xs, ys, cells }
+    }
+
+    /// The blocks covering `start..=end` along one axis.
+    fn blocks(edges: &[usize], start: usize, end: usize) -> Range<usize> {
+        let first = edges.partition_point(|e| *e < start);
+        let last = edges.partition_point(|e| *e <= end).min(edges.len() - 1);
+        first..last.max(first)
+    }
+
+    /// Calls `change` on every block of the instruction's rectangle.
+    fn update(&mut self, instruction: &Instruction, change: impl Fn(&mut u32)) {
+        let width = self.xs.len() - 1;
+        let columns = Self::blocks(&self.xs, instruction.start.0, instruction.end.0);
+        for row in Self::blocks(&self.ys, instruction.start.1, instruction.end.1) {
+            self.cells[row * width..(row + 1) * width][columns.clone()]
+                .iter_mut()
+                .for_each(&change);
+        }
+    }
+
+    fn apply(&mut self, instruction: &Instruction) {
+        match instruction.action {
+            Action::TurnOn => self.update(instruction, |c| *c = 1),
+            Action::TurnOff => self.update(instruction, |c| *c = 0),
+            Action::TOGGLE => self.update(instruction, |c| *c ^= 1),
+        }
+    }
+
+    fn apply_brightness(&mut self, instruction: &Instruction) {
+        match instruction.action {
+            Action::TurnOn => self.update(instruction, |c| *c += 1),
+            Action::TurnOff => self.update(instruction, |c| *c = c.saturating_sub(1)),
+            Action::TOGGLE => self.update(instruction, |c| *c += 2),
+        }
+    }
+
+    /// Same as `Grid::count`, each block weighted by its number of lights.
+    fn count(&self) -> u64 {
+        let width = self.xs.len() - 1;
+        let mut count = 0;
+        for (row, ys) in self.ys.windows(2).enumerate() {
+            let row_count = self.cells[row * width..(row + 1) * width]
+                .iter()
+                .zip(self.xs.windows(2))
+                .map(|(cell, xs)| u64::from(*cell) * (xs[1] - xs[0]) as u64)
+                .sum::<u64>();
+            count += row_count * (ys[1] - ys[0]) as u64;
+        }
+        count
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -187,6 +441,14 @@ mod tests {
             4
         );
         assert_eq!(execute_grid_instruction("turn on 0,0 through 1,1", 4), 4);
+        assert_eq!(
+            execute_grid_instruction("turn on 0,0 through 20,20", 10),
+            100
+        );
+        assert_eq!(
+            execute_grid_instruction("turn on 10,0 through 20,20", 10),
+            0
+        );
     }
     #[test]
     fn test_bright_grid() {
@@ -208,15 +470,82 @@ mod tests {
         );
     }
 
-    fn execute_grid_instruction(s: &str, size: usize) -> u32 {
+    fn execute_grid_instruction(s: &str, size: usize) -> u64 {
         let mut g = Grid::new(size);
         g.apply(&s.parse::<Instruction>().unwrap());
         return g.count();
     }
 
-    fn execute_bright_grid_instruction(s: &str, size: usize) -> u32 {
+    fn execute_bright_grid_instruction(s: &str, size: usize) -> u64 {
         let mut g = Grid::new(size);
         g.apply_brightness(&s.parse::<Instruction>().unwrap());
         return g.count();
     }
+    #[test]
+    fn test_compressed_grid() {
+        let mut state = 6u32;
+        let mut random = |n: u32| {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            (state >> 16) % n
+        };
+        let mut lines = vec![];
+        for _ in 0..300 {
+            let action = ["turn on", "turn off", "toggle"][random(3) as usize];
+            let (x, y) = (random(50), random(50));
+            let (w, h) = (random(20), random(20));
+            lines.push(format!(
+                "{} {},{} through {},{}",
+                action,
+                x,
+                y,
+                (x + w).min(49),
+                (y + h).min(49)
+            ));
+        }
+        // An empty rectangle, start after end.
+        lines.push("turn on 30,30 through 20,40".to_string());
+        let instructions = parse_instructions(&lines.join("\n")).unwrap();
+        for brightness in [false, true] {
+            assert_eq!(
+                count_lights(Engine::Compressed, 50, &instructions, brightness),
+                count_lights(Engine::Dense, 50, &instructions, brightness)
+            );
+        }
+        let instructions =
+            parse_instructions("turn on 0,0 through 20,20\ntoggle 5,8 through 30,9").unwrap();
+        for brightness in [false, true] {
+            assert_eq!(
+                count_lights(Engine::Compressed, 10, &instructions, brightness),
+                count_lights(Engine::Dense, 10, &instructions, brightness)
+            );
+        }
+        let instructions = parse_instructions("turn on 20,20 through 10,10").unwrap();
+        assert_eq!(
+            count_lights(Engine::Compressed, 50, &instructions, false),
+            0
+        );
+        assert_eq!(count_lights(Engine::Compressed, 0, &instructions, true), 0);
+
+        let instructions = parse_instructions(
+            "turn on 0,0 through 999999,999999
+toggle 0,0 through 999999,0
+turn off 499999,499999 through 500000,500000
+toggle 10,10 through 2000000,10",
+        )
+        .unwrap();
+        assert_eq!(
+            count_lights(Engine::Compressed, 1000000, &instructions, false),
+            1000000000000 - 1000000 - 4 - 999990
+        );
+        assert_eq!(
+            count_lights(Engine::Compressed, 1000000, &instructions, true),
+            1000000000000 + 2000000 - 4 + 2 * 999990
+        );
+
+        let options = |args: &[&str]| Options::from_args(args.iter().map(|a| a.to_string()));
+        let compressed = options(&["--size", "1000000", "--engine", "compressed"]).unwrap();
+        assert_eq!(compressed.size, 1000000);
+        assert_eq!(compressed.engine, Engine::Compressed);
+        assert!(options(&["--engine", "sparse"]).is_err());
+    }
 }
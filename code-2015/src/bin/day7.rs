@@ -1,24 +1,42 @@
-use std::{collections::HashMap, error, io, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    error, io,
+    str::FromStr,
+};
+
+use code_2015::{
+    check::{check_lines, sort_by_line, InputProblem},
+    trace,
+};
 
 fn main() -> io::Result<()> {
-    match io::stdin()
-        .lines()
-        .collect::<Result<Vec<String>, _>>()?
-        .iter()
-        .map(|l| l.parse::<Sentence>())
-        .collect::<Result<Vec<Sentence>, Box<dyn error::Error>>>()
-    {
+    trace::init();
+    match trace::parse(7, || {
+        io::stdin()
+            .lines()
+            .collect::<Result<Vec<String>, _>>()
+            .map(|lines| {
+                lines
+                    .iter()
+                    .map(|l| l.parse::<Sentence>())
+                    .collect::<Result<Vec<Sentence>, Box<dyn error::Error>>>()
+            })
+    })? {
         Ok(sentences) => {
             let mut circuit = Circuit::new();
-            circuit.execute(sentences);
-            let part_one_value = circuit.get_value(&"a".into()).unwrap();
+            let part_one_value = trace::solve(7, 1, || {
+                circuit.execute(sentences);
+                circuit.get_value(&"a".into()).unwrap()
+            });
             println!("part one value for wire a is {}", part_one_value);
 
             let sorted = circuit.sorted;
             circuit = Circuit::new();
-            circuit.put_value(&"b".into(), part_one_value);
-            circuit.execute(sorted);
-            let part_two_value = circuit.get_value(&"a".into()).unwrap();
+            let part_two_value = trace::solve(7, 2, || {
+                circuit.put_value(&"b".into(), part_one_value);
+                circuit.execute(sorted);
+                circuit.get_value(&"a".into()).unwrap()
+            });
             println!("part two value for wire a is {}", part_two_value);
         }
         Err(e) => println!("parse instructions failed. {}", e),
@@ -26,6 +44,34 @@ fn main() -> io::Result<()> {
     Ok(())
 }
 
+pub fn part_one(input: &str) -> Result<String, Box<dyn error::Error>> {
+    let mut circuit = Circuit::new();
+    circuit.execute(parse_sentences(input)?);
+    Ok(wire_value(&circuit, "a")?.to_string())
+}
+
+pub fn part_two(input: &str) -> Result<String, Box<dyn error::Error>> {
+    let mut circuit = Circuit::new();
+    circuit.execute(parse_sentences(input)?);
+    let part_one_value = wire_value(&circuit, "a")?;
+
+    let sorted = circuit.sorted;
+    circuit = Circuit::new();
+    circuit.put_value(&"b".into(), part_one_value);
+    circuit.execute(sorted);
+    Ok(wire_value(&circuit, "a")?.to_string())
+}
+
+fn parse_sentences(input: &str) -> Result<Vec<Sentence>, Box<dyn error::Error>> {
+    input.lines().map(|l| l.parse::<Sentence>()).collect()
+}
+
+fn wire_value(circuit: &Circuit, wire: &str) -> Result<u16, Box<dyn error::Error>> {
+    circuit
+        .get_value(&wire.into())
+        .ok_or_else(|| format!("wire {} has no value", wire).into())
+}
+
 #[derive(Debug)]
 enum Operator {
     ASSIGN(String),
@@ -90,6 +136,16 @@ impl Operator {
         }
     }
 
+    fn operands(&self) -> Vec<&String> {
+        match self {
+            Self::ASSIGN(x) | Self::NOT(x) => vec![x],
+            Self::AND { x, y }
+            | Self::OR { x, y }
+            | Self::LSHIFT { x, y }
+            | Self::RSHIFT { x, y } => vec![x, y],
+        }
+    }
+
     fn get_or_parse_operand(&self, circuit: &Circuit, operand: &String) -> Option<u16> {
         circuit.get_value(operand).or(operand.parse::<u16>().ok())
     }
@@ -157,29 +213,68 @@ impl Circuit {
     }
 
     fn execute(&mut self, mut sentences: Vec<Sentence>) {
+        let mut pass = 0;
         loop {
             if sentences.is_empty() {
                 return;
             }
 
+            pass += 1;
+            let resolved_before = self.sorted.len();
             sentences = sentences
                 .into_iter()
                 .filter_map(|s| match &s.execute(self) {
                     Some(_) => {
+                        tracing::trace!(sentence = ?s, "sentence resolved");
                         self.sorted.push(s);
                         None
                     }
                     None => Some(s),
                 })
                 .collect::<Vec<Sentence>>();
+            tracing::debug!(
+                pass,
+                resolved = self.sorted.len() - resolved_before,
+                pending = sentences.len(),
+                "circuit pass"
+            );
         }
     }
+}
 
-    fn println_sentences(&self) {
-        for s in self.sorted.iter() {
-            println!("s {:?}", s)
+pub fn check_input(input: &str) -> Vec<InputProblem> {
+    let (sentences, mut problems) = check_lines::<Sentence>(input);
+    let mut drivers: HashMap<&String, usize> = HashMap::new();
+    for (line, s) in sentences.iter() {
+        if let Some(first) = drivers.get(&s.assign_to) {
+            problems.push(InputProblem::at_line(
+                *line,
+                format!(
+                    "wire {} is already driven by the sentence on line {}",
+                    s.assign_to, first
+                ),
+            ));
+        } else {
+            drivers.insert(&s.assign_to, *line);
         }
     }
+
+    let mut reported = HashSet::new();
+    for (line, s) in sentences.iter() {
+        for operand in s.operator.operands() {
+            if operand.parse::<u16>().is_err()
+                && !drivers.contains_key(operand)
+                && reported.insert(operand)
+            {
+                problems.push(InputProblem::at_line(
+                    *line,
+                    format!("wire {} is never driven", operand),
+                ));
+            }
+        }
+    }
+    sort_by_line(&mut problems);
+    problems
 }
 
 #[cfg(test)]
@@ -212,4 +307,19 @@ mod tests {
         assert_eq!(c.get_value(&"h".into()).unwrap(), 65412);
         assert_eq!(c.get_value(&"i".into()).unwrap(), 65079);
     }
+    #[test]
+    fn test_check_input() {
+        let problems = check_input("123 -> x\nx AND y -> d\n456 -> x\nx OR -> e");
+        assert_eq!(
+            problems
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<String>>(),
+            vec![
+                "line 2: wire y is never driven",
+                "line 3: wire x is already driven by the sentence on line 1",
+                "line 4: sentence: \"x OR -> e\" is not in valid format",
+            ]
+        );
+    }
 }
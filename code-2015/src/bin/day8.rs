@@ -1,13 +1,20 @@
 use std::{error, io, str::Chars};
 
+use code_2015::{check::InputProblem, trace};
+
 fn main() -> io::Result<()> {
-    match io::stdin().lines().collect::<Result<Vec<String>, _>>() {
+    trace::init();
+    match trace::parse(8, || {
+        io::stdin().lines().collect::<Result<Vec<String>, _>>()
+    }) {
         Ok(lines) => {
             let original: usize = lines.iter().map(|s| s.len()).sum();
-            let memory: usize = lines
-                .iter()
-                .map(|s| count_characters_in_memory(s.as_str()).unwrap())
-                .sum();
+            let memory: usize = trace::solve(8, 1, || {
+                lines
+                    .iter()
+                    .map(|s| count_characters_in_memory(s.as_str()).unwrap())
+                    .sum()
+            });
             println!("num of orignal characters is: {}", original);
             println!("num of characters in memory is: {}", memory);
             println!(
@@ -15,7 +22,9 @@ fn main() -> io::Result<()> {
                 original - memory
             );
 
-            let encoded: usize = lines.iter().map(|s| encode(s.as_str()).len()).sum();
+            let encoded: usize = trace::solve(8, 2, || {
+                lines.iter().map(|s| encode(s.as_str()).len()).sum()
+            });
             println!("num of encoded characters is: {}", encoded);
             println!(
                 "difference from encoded to original is: {}",
@@ -27,8 +36,24 @@ fn main() -> io::Result<()> {
     Ok(())
 }
 
+pub fn part_one(input: &str) -> Result<String, Box<dyn error::Error>> {
+    let mut diff = 0;
+    for line in input.lines() {
+        diff += line.len() - count_characters_in_memory(line)?;
+    }
+    Ok(diff.to_string())
+}
+
+pub fn part_two(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(input
+        .lines()
+        .map(|l| encode(l).len() - l.len())
+        .sum::<usize>()
+        .to_string())
+}
+
 fn count_characters_in_memory(s: &str) -> Result<usize, Box<dyn error::Error>> {
-    if !s.starts_with("\"") || !s.ends_with("\"") {
+    if s.len() < 2 || !s.starts_with("\"") || !s.ends_with("\"") {
         return Err(format!("string: \"{}\" is not quoted by double quote", s).into());
     }
 
@@ -66,6 +91,18 @@ fn count_characters_in_memory(s: &str) -> Result<usize, Box<dyn error::Error>> {
     }
 }
 
+pub fn check_input(input: &str) -> Vec<InputProblem> {
+    input
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            count_characters_in_memory(line)
+                .err()
+                .map(|e| InputProblem::at_line(i + 1, e.to_string()))
+        })
+        .collect()
+}
+
 fn encode(s: &str) -> String {
     return format!(
         "\"{}\"",
@@ -106,7 +143,6 @@ mod tests {
 
     #[test]
     fn test_encode() {
-        println!("asdfsdf {}", encode(""));
         assert_eq!(encode("\"\"").len(), 6);
         assert_eq!(encode("\"abc\"").len(), 9);
         assert_eq!(encode("\"aaa\\\"aaa\"").len(), 16);
@@ -5,22 +5,68 @@ use std::{
     vec,
 };
 
+use code_2015::{
+    check::{check_lines, sort_by_line, InputProblem},
+    report::Visual,
+    trace,
+};
+
 fn main() -> io::Result<()> {
-    match io::stdin().lines().collect::<Result<Vec<String>, _>>() {
+    trace::init();
+    match trace::parse(9, || {
+        io::stdin().lines().collect::<Result<Vec<String>, _>>()
+    }) {
         Ok(lines) => {
-            let routes = lines
-                .iter()
-                .map(|s| s.parse::<Route>().unwrap())
-                .collect::<Vec<Route>>();
-            let m = Map::new(routes);
-            println!("shortest: {}", m.shortest_route());
-            println!("longest: {}", m.longest_route());
+            let m = trace::parse(9, || {
+                let routes = lines
+                    .iter()
+                    .map(|s| s.parse::<Route>().unwrap())
+                    .collect::<Vec<Route>>();
+                Map::new(routes)
+            });
+            println!("shortest: {}", trace::solve(9, 1, || m.shortest_route()));
+            println!("longest: {}", trace::solve(9, 2, || m.longest_route()));
         }
         Err(e) => println!("parse route failed. {}", e),
     }
     Ok(())
 }
 
+pub fn part_one(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(parse_map(input)?.shortest_route().to_string())
+}
+
+pub fn part_two(input: &str) -> Result<String, Box<dyn error::Error>> {
+    Ok(parse_map(input)?.longest_route().to_string())
+}
+
+pub fn visualize(input: &str) -> Result<Vec<Visual>, Box<dyn error::Error>> {
+    let map = parse_map(input)?;
+    let route = route_permutation(map.locations.clone())
+        .into_iter()
+        .min_by_key(|r| map.route_distance(r))
+        .ok_or("no route found")?;
+    let links = route
+        .iter()
+        .zip(route.iter().skip(1))
+        .map(|(from, to)| map.route_distances[from][to].to_string())
+        .collect();
+    Ok(vec![Visual::Ring {
+        title: format!("Shortest route, {}", map.route_distance(&route)),
+        nodes: route.iter().map(|l| format!("{:?}", l)).collect(),
+        links,
+        closed: false,
+    }])
+}
+
+fn parse_map(input: &str) -> Result<Map, Box<dyn error::Error>> {
+    let routes = input
+        .lines()
+        .map(|s| s.parse::<Route>())
+        .collect::<Result<Vec<Route>, _>>()?;
+    Ok(Map::new(routes))
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 enum Location {
     Tristram,
@@ -114,31 +160,28 @@ impl Map {
                 .unwrap()
                 .clone();
         }
-        return route_permutation(self.locations.clone())
+        self.evaluate_routes().min().unwrap()
+    }
+
+    fn evaluate_routes(&self) -> impl Iterator<Item = u32> + '_ {
+        let routes = route_permutation(self.locations.clone());
+        let total = routes.len();
+        routes.into_iter().enumerate().map(move |(i, route)| {
+            trace::permutations_evaluated(i + 1, total);
+            self.route_distance(&route)
+        })
+    }
+
+    fn route_distance(&self, route: &[Location]) -> u32 {
+        route
             .iter()
-            .map(|routes| -> u32 {
-                return routes
-                    .iter()
-                    .zip(routes.iter().skip(1))
-                    .map(|(from, to)| self.route_distances.get(from).unwrap().get(to).unwrap())
-                    .sum();
-            })
-            .min()
-            .unwrap();
+            .zip(route.iter().skip(1))
+            .map(|(from, to)| self.route_distances[from][to])
+            .sum()
     }
 
     fn longest_route(&self) -> u32 {
-        return route_permutation(self.locations.clone())
-            .iter()
-            .map(|routes| -> u32 {
-                return routes
-                    .iter()
-                    .zip(routes.iter().skip(1))
-                    .map(|(from, to)| self.route_distances.get(from).unwrap().get(to).unwrap())
-                    .sum();
-            })
-            .max()
-            .unwrap();
+        self.evaluate_routes().max().unwrap()
     }
 }
 
@@ -158,6 +201,59 @@ fn record_route_distance(
         .insert(to.clone(), distance);
 }
 
+pub fn check_input(input: &str) -> Vec<InputProblem> {
+    let (routes, mut problems) = check_lines::<Route>(input);
+    let mut distances: HashMap<(Location, Location), (usize, u32)> = HashMap::new();
+    let mut locations = vec![];
+    for (line, r) in routes.iter() {
+        for l in [&r.from, &r.to] {
+            if !locations.contains(l) {
+                locations.push(l.clone());
+            }
+        }
+        if r.from == r.to {
+            problems.push(InputProblem::at_line(
+                *line,
+                format!("route from {:?} to itself", r.from),
+            ));
+            continue;
+        }
+        for key in [
+            (r.from.clone(), r.to.clone()),
+            (r.to.clone(), r.from.clone()),
+        ] {
+            if let Some((first, distance)) = distances.get(&key) {
+                if *distance != r.distance {
+                    problems.push(InputProblem::at_line(
+                        *line,
+                        format!(
+                            "distance between {:?} and {:?} conflicts with line {}",
+                            r.from, r.to, first
+                        ),
+                    ));
+                }
+                break;
+            }
+        }
+        distances.insert((r.from.clone(), r.to.clone()), (*line, r.distance));
+    }
+
+    for (i, from) in locations.iter().enumerate() {
+        for to in locations[i + 1..].iter() {
+            if !distances.contains_key(&(from.clone(), to.clone()))
+                && !distances.contains_key(&(to.clone(), from.clone()))
+            {
+                problems.push(InputProblem::in_input(format!(
+                    "distance between {:?} and {:?} is missing",
+                    from, to
+                )));
+            }
+        }
+    }
+    sort_by_line(&mut problems);
+    problems
+}
+
 fn route_permutation(locations: Vec<Location>) -> Vec<Vec<Location>> {
     if locations.len() < 2 {
         panic!("not enough locations to compute combinations");
@@ -215,4 +311,21 @@ mod tests {
         assert_eq!(map.shortest_route(), 36);
         assert_eq!(map.longest_route(), 153);
     }
+    #[test]
+    fn test_check_input() {
+        let problems = check_input(
+            "Tristram to Arbre = 132\nTristram to Faerun = 21\nArbre to Tristram = 15\nArbre to Moria = 1",
+        );
+        assert_eq!(
+            problems
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<String>>(),
+            vec![
+                "line 3: distance between Arbre and Tristram conflicts with line 1",
+                "line 4: parse Route from: \"Arbre to Moria = 1\" failed",
+                "input: distance between Arbre and Faerun is missing",
+            ]
+        );
+    }
 }
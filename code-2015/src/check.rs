@@ -0,0 +1,126 @@
+use std::{fmt, str::FromStr};
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct InputProblem {
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl InputProblem {
+    pub fn at_line(line: usize, message: impl Into<String>) -> InputProblem {
+        InputProblem {
+            line: Some(line),
+            column: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn at(line: usize, column: usize, message: impl Into<String>) -> InputProblem {
+        InputProblem {
+            line: Some(line),
+            column: Some(column),
+            message: message.into(),
+        }
+    }
+
+    /// A problem that is not caused by a single line, e.g. a missing entry.
+    pub fn in_input(message: impl Into<String>) -> InputProblem {
+        InputProblem {
+            line: None,
+            column: None,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for InputProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => {
+                write!(f, "line {}, column {}: {}", line, column, self.message)
+            }
+            (Some(line), None) => write!(f, "line {}: {}", line, self.message),
+            _ => write!(f, "input: {}", self.message),
+        }
+    }
+}
+
+/// Parses every line of `input` and returns the parsed values together with
+/// their 1-based line numbers, plus one problem for each line that failed.
+pub fn check_lines<T>(input: &str) -> (Vec<(usize, T)>, Vec<InputProblem>)
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    let mut parsed = vec![];
+    let mut problems = vec![];
+    for (i, line) in input.lines().enumerate() {
+        match line.parse::<T>() {
+            Ok(v) => parsed.push((i + 1, v)),
+            Err(e) => problems.push(InputProblem::at_line(i + 1, e.to_string())),
+        }
+    }
+    if parsed.is_empty() && problems.is_empty() {
+        problems.push(InputProblem::in_input("input is empty"));
+    }
+    (parsed, problems)
+}
+
+/// Orders problems by line, keeping problems about the whole input last.
+pub fn sort_by_line(problems: &mut [InputProblem]) {
+    problems.sort_by_key(|p| p.line.unwrap_or(usize::MAX));
+}
+
+/// Checks a puzzle input that the solver reads as a single line, reporting
+/// every character rejected by `valid` and any line after the first one.
+pub fn check_single_line(input: &str, valid: impl Fn(char) -> bool) -> Vec<InputProblem> {
+    let mut problems = vec![];
+    for (i, line) in input.lines().enumerate() {
+        if i > 0 {
+            if !line.trim().is_empty() {
+                problems.push(InputProblem::at_line(
+                    i + 1,
+                    "only the first line of the input is read",
+                ));
+            }
+            continue;
+        }
+        for (j, c) in line.chars().enumerate() {
+            if !valid(c) {
+                problems.push(InputProblem::at(
+                    i + 1,
+                    j + 1,
+                    format!("unexpected character {:?}", c),
+                ));
+            }
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_check_lines() {
+        let (parsed, problems) = check_lines::<u32>("1\nx\n3");
+        assert_eq!(parsed, vec![(1, 1), (3, 3)]);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].line, Some(2));
+
+        let (_, problems) = check_lines::<u32>("");
+        assert_eq!(problems, vec![InputProblem::in_input("input is empty")]);
+    }
+    #[test]
+    fn test_check_single_line() {
+        let problems = check_single_line("(a)\n)\n\n", |c| c == '(' || c == ')');
+        assert_eq!(
+            problems,
+            vec![
+                InputProblem::at(1, 2, "unexpected character 'a'"),
+                InputProblem::at_line(2, "only the first line of the input is read"),
+            ]
+        );
+    }
+}
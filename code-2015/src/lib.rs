@@ -0,0 +1,4 @@
+pub mod check;
+pub mod profile;
+pub mod report;
+pub mod trace;
@@ -0,0 +1,144 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, MutexGuard,
+    },
+};
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+/// Held by `measure`, which resets the global peak.
+static MEASURING: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    static IN_MEASURE: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Marks the thread as measuring until dropped, even when `f` panics.
+struct Measuring<'a> {
+    _lock: MutexGuard<'a, ()>,
+}
+
+impl Drop for Measuring<'_> {
+    fn drop(&mut self) {
+        IN_MEASURE.with(|m| m.set(false));
+    }
+}
+
+/// Wraps the system allocator and counts heap usage. It only takes effect in
+/// a binary that installs it with `#[global_allocator]`.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            record_allocation(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            record_allocation(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+            record_allocation(new_size);
+        }
+        new_ptr
+    }
+}
+
+fn record_allocation(size: usize) {
+    let current = CURRENT.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(current, Ordering::Relaxed);
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED.fetch_add(size, Ordering::Relaxed);
+}
+
+/// Heap usage of a measured closure. A `realloc` counts as one allocation of
+/// the new size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// Highest heap size reached, on top of what was in use before.
+    pub peak_bytes: usize,
+    pub allocations: usize,
+    pub allocated_bytes: usize,
+}
+
+/// Runs `f` and returns the heap usage it caused. The numbers are only
+/// meaningful when `CountingAllocator` is the global allocator and no other
+/// thread allocates at the same time.
+///
+/// Each call resets the global peak, so calls from different threads wait for
+/// each other, and calling `measure` inside `f` panics.
+pub fn measure<T>(f: impl FnOnce() -> T) -> (T, MemoryUsage) {
+    assert!(
+        !IN_MEASURE.with(|m| m.replace(true)),
+        "measure can't be nested"
+    );
+    let _measuring = Measuring {
+        _lock: MEASURING.lock().unwrap_or_else(|e| e.into_inner()),
+    };
+    let base = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(base, Ordering::Relaxed);
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let allocated = ALLOCATED.load(Ordering::Relaxed);
+
+    let ret = f();
+    let usage = MemoryUsage {
+        peak_bytes: PEAK.load(Ordering::Relaxed).saturating_sub(base),
+        allocations: ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        allocated_bytes: ALLOCATED.load(Ordering::Relaxed) - allocated,
+    };
+    (ret, usage)
+}
+
+/// Formats a byte count with a binary unit, e.g. `1.5 MiB`.
+pub struct Bytes(pub usize);
+
+impl fmt::Display for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+        if self.0 < 1024 {
+            return write!(f, "{} B", self.0);
+        }
+        let mut value = self.0 as f64 / 1024.0;
+        let mut unit = 0;
+        while value >= 1024.0 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        write!(f, "{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bytes() {
+        assert_eq!(Bytes(12).to_string(), "12 B");
+        assert_eq!(Bytes(1536).to_string(), "1.5 KiB");
+        assert_eq!(Bytes(3 << 20).to_string(), "3.0 MiB");
+    }
+}
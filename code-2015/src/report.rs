@@ -0,0 +1,325 @@
+use std::{fmt::Write, time::Duration};
+
+use crate::profile::{Bytes, MemoryUsage};
+
+const WIDTH: f64 = 600.0;
+const COLORS: [&str; 9] = [
+    "#e6194b", "#3cb44b", "#4363d8", "#f58231", "#911eb4", "#42d4f4", "#f032e6", "#9a6324",
+    "#808000",
+];
+
+/// Something a day can draw from its puzzle input in the run report.
+pub enum Visual {
+    /// A dense row-major grid of values, brighter cells have higher values.
+    Grid {
+        title: String,
+        width: usize,
+        height: usize,
+        values: Vec<u32>,
+    },
+    /// Nodes placed around a circle and linked in order, each link carrying a
+    /// label. The last node links back to the first one when `closed` is set.
+    Ring {
+        title: String,
+        nodes: Vec<String>,
+        links: Vec<String>,
+        closed: bool,
+    },
+    /// Named series of values sampled at every step of time.
+    Series {
+        title: String,
+        series: Vec<(String, Vec<u32>)>,
+    },
+}
+
+pub struct PartReport {
+    pub part: u32,
+    pub answer: Result<String, String>,
+    pub elapsed: Duration,
+    pub memory: MemoryUsage,
+}
+
+pub struct DayReport {
+    pub day: u32,
+    pub parts: Vec<PartReport>,
+    pub visuals: Result<Vec<Visual>, String>,
+}
+
+/// Renders a self-contained HTML page with an answer table and every visual.
+pub fn render(days: &[DayReport]) -> String {
+    let mut out = String::new();
+    out.push_str(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Advent of Code 2015</title>\n<style>\n\
+         body { font-family: sans-serif; margin: 2em; }\n\
+         table { border-collapse: collapse; }\n\
+         th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; }\n\
+         td.num { text-align: right; }\n\
+         .error { color: #c00; }\n\
+         svg { border: 1px solid #ccc; background: #fff; }\n\
+         </style>\n</head>\n<body>\n<h1>Advent of Code 2015</h1>\n",
+    );
+
+    out.push_str(
+        "<table>\n<tr><th>Day</th><th>Part</th><th>Answer</th><th>Time</th>\
+         <th>Peak heap</th><th>Allocations</th></tr>\n",
+    );
+    for d in days {
+        for p in d.parts.iter() {
+            let answer = match &p.answer {
+                Ok(a) => escape(a),
+                Err(e) => format!("<span class=\"error\">{}</span>", escape(e)),
+            };
+            let _ = writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td class=\"num\">{:.3?}</td>\
+                 <td class=\"num\">{}</td><td class=\"num\">{}</td></tr>",
+                d.day,
+                p.part,
+                answer,
+                p.elapsed,
+                Bytes(p.memory.peak_bytes),
+                p.memory.allocations
+            );
+        }
+    }
+    out.push_str("</table>\n");
+
+    for d in days {
+        match &d.visuals {
+            Ok(visuals) if visuals.is_empty() => continue,
+            Ok(visuals) => {
+                let _ = writeln!(out, "<h2 id=\"day{0}\">Day {0}</h2>", d.day);
+                for v in visuals {
+                    out.push_str(&render_visual(v));
+                }
+            }
+            Err(e) => {
+                let _ = writeln!(
+                    out,
+                    "<h2 id=\"day{0}\">Day {0}</h2>\n<p class=\"error\">{1}</p>",
+                    d.day,
+                    escape(e)
+                );
+            }
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn render_visual(visual: &Visual) -> String {
+    let (title, svg) = match visual {
+        Visual::Grid {
+            title,
+            width,
+            height,
+            values,
+        } => (title, grid_svg(*width, *height, values)),
+        Visual::Ring {
+            title,
+            nodes,
+            links,
+            closed,
+        } => (title, ring_svg(nodes, links, *closed)),
+        Visual::Series { title, series } => (title, series_svg(series)),
+    };
+    format!("<h3>{}</h3>\n{}\n", escape(title), svg)
+}
+
+fn grid_svg(width: usize, height: usize, values: &[u32]) -> String {
+    // Big grids are averaged over square blocks to keep the page small.
+    let block = width.max(height).div_ceil(200).max(1);
+    let cols = width.div_ceil(block);
+    let rows = height.div_ceil(block);
+    let mut shades = vec![0.0; cols * rows];
+    for y in 0..height {
+        for x in 0..width {
+            shades[(y / block) * cols + x / block] += values[y * width + x] as f64;
+        }
+    }
+    let max = shades.iter().cloned().fold(0.0, f64::max);
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" viewBox=\"0 0 {} {}\" shape-rendering=\"crispEdges\">\n<rect width=\"{}\" height=\"{}\" fill=\"#111\"/>\n",
+        WIDTH,
+        WIDTH * rows as f64 / cols.max(1) as f64,
+        cols,
+        rows,
+        cols,
+        rows
+    );
+    for y in 0..rows {
+        // Neighbouring blocks with the same shade are merged into one rect.
+        let mut x = 0;
+        while x < cols {
+            let level = shade_level(shades[y * cols + x], max);
+            let mut run = 1;
+            while x + run < cols && shade_level(shades[y * cols + x + run], max) == level {
+                run += 1;
+            }
+            if level > 0 {
+                let _ = writeln!(
+                    out,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"1\" fill=\"#f5c400\" fill-opacity=\"{:.1}\"/>",
+                    x,
+                    y,
+                    run,
+                    level as f64 / 10.0
+                );
+            }
+            x += run;
+        }
+    }
+    out.push_str("</svg>");
+    out
+}
+
+fn shade_level(value: f64, max: f64) -> u32 {
+    if max <= 0.0 {
+        return 0;
+    }
+    (value / max * 10.0).ceil() as u32
+}
+
+fn ring_svg(nodes: &[String], links: &[String], closed: bool) -> String {
+    let center = WIDTH / 2.0;
+    let radius = center - 80.0;
+    let positions = (0..nodes.len())
+        .map(|i| {
+            let angle = 2.0 * std::f64::consts::PI * i as f64 / nodes.len() as f64
+                - std::f64::consts::FRAC_PI_2;
+            (center + radius * angle.cos(), center + radius * angle.sin())
+        })
+        .collect::<Vec<(f64, f64)>>();
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0:.0}\" height=\"{0:.0}\" font-size=\"12\" text-anchor=\"middle\">\n",
+        WIDTH
+    );
+    for (i, label) in links.iter().enumerate() {
+        if i + 1 >= nodes.len() && !closed {
+            break;
+        }
+        let from = positions[i % nodes.len()];
+        let to = positions[(i + 1) % nodes.len()];
+        let _ = writeln!(
+            out,
+            "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"#4363d8\" stroke-width=\"2\"/>\n<text x=\"{:.1}\" y=\"{:.1}\" fill=\"#4363d8\">{}</text>",
+            from.0,
+            from.1,
+            to.0,
+            to.1,
+            (from.0 + to.0) / 2.0,
+            (from.1 + to.1) / 2.0 - 4.0,
+            escape(label)
+        );
+    }
+    for (i, name) in nodes.iter().enumerate() {
+        let (x, y) = positions[i];
+        let _ = writeln!(
+            out,
+            "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"6\" fill=\"#e6194b\"/>\n<text x=\"{:.1}\" y=\"{:.1}\">{}</text>",
+            x,
+            y,
+            x,
+            y + if y > center { 22.0 } else { -12.0 },
+            escape(name)
+        );
+    }
+    out.push_str("</svg>");
+    out
+}
+
+fn series_svg(series: &[(String, Vec<u32>)]) -> String {
+    let height = WIDTH / 2.0;
+    let steps = series.iter().map(|s| s.1.len()).max().unwrap_or(0).max(2);
+    let max = series
+        .iter()
+        .flat_map(|s| s.1.iter())
+        .max()
+        .cloned()
+        .unwrap_or(0)
+        .max(1) as f64;
+
+    let mut out = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" font-size=\"12\">\n",
+        WIDTH + 120.0,
+        height
+    );
+    for (i, (name, values)) in series.iter().enumerate() {
+        let color = COLORS[i % COLORS.len()];
+        let points = values
+            .iter()
+            .enumerate()
+            .map(|(t, v)| {
+                format!(
+                    "{:.1},{:.1}",
+                    t as f64 * WIDTH / (steps - 1) as f64,
+                    height - *v as f64 * (height - 10.0) / max
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(" ");
+        let _ = writeln!(
+            out,
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"1.5\"/>\n<text x=\"{:.0}\" y=\"{}\" fill=\"{}\">{} ({})</text>",
+            points,
+            color,
+            WIDTH + 10.0,
+            16 + i * 16,
+            color,
+            escape(name),
+            values.last().cloned().unwrap_or(0)
+        );
+    }
+    out.push_str("</svg>");
+    out
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_render() {
+        let days = vec![DayReport {
+            day: 3,
+            parts: vec![PartReport {
+                part: 1,
+                answer: Ok("<4>".into()),
+                elapsed: Duration::from_millis(2),
+                memory: MemoryUsage {
+                    peak_bytes: 2048,
+                    allocations: 3,
+                    allocated_bytes: 4096,
+                },
+            }],
+            visuals: Ok(vec![Visual::Grid {
+                title: "houses".into(),
+                width: 2,
+                height: 1,
+                values: vec![1, 1],
+            }]),
+        }];
+        let html = render(&days);
+        assert!(html.contains(
+            "<td>&lt;4&gt;</td><td class=\"num\">2.000ms</td>\
+             <td class=\"num\">2.0 KiB</td><td class=\"num\">3</td>"
+        ));
+        assert!(html.contains("<h2 id=\"day3\">Day 3</h2>"));
+        assert!(html.contains("<h3>houses</h3>"));
+    }
+    #[test]
+    fn test_grid_merges_runs() {
+        let svg = grid_svg(4, 1, &[1, 1, 0, 2]);
+        assert!(svg.contains("<rect x=\"0\" y=\"0\" width=\"2\" height=\"1\""));
+        assert!(svg.contains("<rect x=\"3\" y=\"0\" width=\"1\" height=\"1\""));
+    }
+}
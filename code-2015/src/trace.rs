@@ -0,0 +1,54 @@
+use std::{env, fs::File, str::FromStr, sync::Mutex};
+
+use tracing::Level;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+/// Name of the environment variable holding the path of the trace log file.
+pub const TRACE_FILE_ENV: &str = "AOC_TRACE";
+/// Name of the environment variable overriding the default `debug` level.
+pub const TRACE_LEVEL_ENV: &str = "AOC_TRACE_LEVEL";
+
+/// Writes spans and events as JSON lines to the file named by `AOC_TRACE`.
+/// Nothing is recorded when the variable is not set.
+pub fn init() {
+    let path = match env::var(TRACE_FILE_ENV) {
+        Ok(p) => p,
+        Err(_) => return,
+    };
+    let level = env::var(TRACE_LEVEL_ENV)
+        .ok()
+        .and_then(|l| Level::from_str(l.as_str()).ok())
+        .unwrap_or(Level::DEBUG);
+    match File::create(&path) {
+        Ok(file) => tracing_subscriber::fmt()
+            .json()
+            .with_writer(Mutex::new(file))
+            .with_span_events(FmtSpan::CLOSE)
+            .with_max_level(level)
+            .init(),
+        Err(e) => eprintln!("create trace file: \"{}\" failed, {}", path, e),
+    }
+}
+
+/// Reports progress of a brute force search over permutations, rate limited
+/// to one event every 1000 permutations plus one for the last permutation.
+pub fn permutations_evaluated(evaluated: usize, total: usize) {
+    if evaluated.is_multiple_of(1000) || evaluated == total {
+        tracing::debug!(evaluated, total, "permutations evaluated");
+    }
+}
+
+/// Runs the input reading and parsing phase of a day inside a `parse` span.
+pub fn parse<T>(day: u32, f: impl FnOnce() -> T) -> T {
+    tracing::info_span!("parse", day).in_scope(f)
+}
+
+/// Runs one part of a day inside a `solve` span.
+pub fn solve<T>(day: u32, part: u32, f: impl FnOnce() -> T) -> T {
+    tracing::info_span!("solve", day, part).in_scope(f)
+}
+
+/// Runs a solver answering every part of a day in one go inside a `solve` span.
+pub fn solve_all<T>(day: u32, f: impl FnOnce() -> T) -> T {
+    tracing::info_span!("solve", day).in_scope(f)
+}
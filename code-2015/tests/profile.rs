@@ -0,0 +1,30 @@
+//! `measure` reads global counters, so it's tested in its own binary where no
+//! other test allocates at the same time.
+
+use std::panic;
+
+use code_2015::profile::{measure, CountingAllocator};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[test]
+fn test_measure() {
+    let (len, usage) = measure(|| vec![0u8; 1 << 20].len());
+    assert_eq!(len, 1 << 20);
+    assert!(usage.peak_bytes >= 1 << 20);
+    assert!(usage.allocations >= 1);
+    assert!(usage.allocated_bytes >= 1 << 20);
+
+    let (_, usage) = measure(|| {
+        let first = vec![0u8; 1 << 20];
+        drop(first);
+        vec![0u8; 1 << 19].len()
+    });
+    assert!(usage.peak_bytes >= 1 << 20 && usage.peak_bytes < 3 << 19);
+    assert!(usage.allocated_bytes >= 3 << 19);
+
+    assert!(panic::catch_unwind(|| measure(|| measure(|| ()))).is_err());
+    // The failed call leaves the next one working.
+    assert_eq!(measure(|| 1).0, 1);
+}